@@ -0,0 +1,145 @@
+//! Adapters turning closures into format tables and `Fmt`s, for when writing
+//! a dedicated type with a trait `impl` is too much ceremony.
+
+use std::collections::HashMap;
+
+use {BoxOrRef, Fmt, FormatTable, SingleFmtError};
+
+/// A format table that produces its `Fmt`s by calling a closure with the
+/// name of the requested `Fmt`. Returning `None` from the closure means that
+/// there's no such `Fmt`.
+/// ```
+/// use pfmt::{Fmt, FormatTable};
+/// use pfmt::adapters::FnTable;
+///
+/// let table = FnTable::new(|name| match name.parse::<i32>() {
+///     Ok(i) => Some(Box::new(i * i) as Box<Fmt>),
+///     Err(_) => None,
+/// });
+/// let s = table.format("{2}, {12}").unwrap();
+/// assert_eq!(s, "4, 144");
+/// ```
+pub struct FnTable<F>(pub F);
+
+impl<F> FnTable<F>
+where
+    F: Fn(&str) -> Option<Box<dyn Fmt>>,
+{
+    pub fn new(f: F) -> Self {
+        FnTable(f)
+    }
+}
+
+impl<F> FormatTable for FnTable<F>
+where
+    F: Fn(&str) -> Option<Box<dyn Fmt>>,
+{
+    fn get_fmt<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        (self.0)(name).map(BoxOrRef::Boxed)
+    }
+}
+
+/// A `Fmt` that formats itself by calling a closure with the placeholder's
+/// sub-name (the part of the name after this `Fmt`), arguments, flags and
+/// options. The closure's output is used as is, so if you want to honor
+/// the common options, call `util::apply_common_options` from it.
+/// ```
+/// use std::collections::HashMap;
+/// use pfmt::{Fmt, FormatTable, util};
+/// use pfmt::adapters::FnFmt;
+///
+/// let greeting = FnFmt::new(|_name, args, _flags, options| {
+///     let mut s = format!("Hello, {}!", args.join(" and "));
+///     util::apply_common_options(&mut s, options)?;
+///     Ok(s)
+/// });
+/// let mut table: HashMap<&str, &Fmt> = HashMap::new();
+/// table.insert("hello", &greeting);
+/// let s = table.format("{hello{Alice:Bob}::width=r22}").unwrap();
+/// assert_eq!(s, " Hello, Alice and Bob!");
+/// ```
+pub struct FnFmt<F>(pub F);
+
+impl<F> FnFmt<F>
+where
+    F: Fn(&[String], &[String], &[char], &HashMap<String, String>) -> Result<String, SingleFmtError>,
+{
+    pub fn new(f: F) -> Self {
+        FnFmt(f)
+    }
+}
+
+impl<F> Fmt for FnFmt<F>
+where
+    F: Fn(&[String], &[String], &[char], &HashMap<String, String>) -> Result<String, SingleFmtError>,
+{
+    fn format(
+        &self,
+        _full_name: &[String],
+        name: &[String],
+        args: &[String],
+        flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        (self.0)(name, args, flags, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name closures;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use adapters::{FnFmt, FnTable};
+        use {util, Fmt, FormatTable, FormattingError, SingleFmtError};
+
+        test table() {
+            let table = FnTable::new(|name| {
+                if name == "answer" {
+                    Some(Box::new(42) as Box<dyn Fmt>)
+                } else {
+                    None
+                }
+            });
+            let s = table.format("{answer:x}");
+            assert_that!(&s, eq(Ok("2a".to_string())));
+            let s = table.format("{question}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("question".to_string(), vec![]))));
+        }
+
+        test fmt_receives_everything() {
+            let f = FnFmt::new(|name, args, flags, options| {
+                Ok(format!("{:?} {:?} {:?} {:?}", name, args, flags, options.get("o")))
+            });
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("f", &f);
+            let s = table.format("{f.a.b{x}:fl:o=1}").unwrap();
+            assert_that!(&s.as_str(),
+                         eq("[\"a\", \"b\"] [\"x\"] ['f', 'l'] Some(\"1\")"));
+        }
+
+        test fmt_errors() {
+            let f = FnFmt::new(|_, _, _, _| Err(SingleFmtError::UnknownFlag('q')));
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("f", &f);
+            let s = table.format("{f}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFlag('q'))));
+        }
+
+        test table_of_closures() {
+            let table = FnTable::new(|name| {
+                let name = name.to_string();
+                Some(Box::new(FnFmt::new(move |_, _, _, options| {
+                    let mut s = name.to_uppercase();
+                    util::apply_common_options(&mut s, options)?;
+                    Ok(s)
+                })) as Box<dyn Fmt>)
+            });
+            let s = table.format("{abc::width=r4}").unwrap();
+            assert_that!(&s.as_str(), eq(" ABC"));
+        }
+
+    }
+}
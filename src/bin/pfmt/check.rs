@@ -0,0 +1,126 @@
+//! `pfmt check`: reporting syntax errors and likely mistakes in templates.
+
+use std::collections::HashMap;
+
+use pfmt::lint::{self, Kind, Warning};
+use pfmt::syntax::Syntax;
+
+use data;
+use errors;
+
+/// Everything the `check` command line asks for.
+#[derive(Debug, Default, PartialEq)]
+pub struct CheckOptions {
+    pub files: Vec<String>,
+    pub declarations: HashMap<String, Kind>,
+    pub help: bool,
+}
+
+pub fn parse_args(args: &[String]) -> Result<CheckOptions, String> {
+    let mut options = CheckOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-d" | "--declare" => {
+                let declaration = iter
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                let (name, kind_name) = data::parse_pair(declaration)?;
+                let kind = Kind::from_name(&kind_name)
+                    .ok_or_else(|| format!("unknown type '{}'", kind_name))?;
+                options.declarations.insert(name, kind);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option '{}'", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() && !options.help {
+        return Err("no template given".to_string());
+    }
+    Ok(options)
+}
+
+/// Checks a template, giving a line of the report for every problem found,
+/// in the `FILE:LINE:COLUMN: message` form.
+pub fn check_template(
+    path: &str,
+    template: &str,
+    declarations: &HashMap<String, Kind>,
+) -> Vec<String> {
+    match lint::check(template, &Syntax::default(), declarations) {
+        Ok(warnings) => warnings
+            .iter()
+            .map(|(pos, warning)| {
+                let message = describe(warning);
+                format!("{}:{}:{}: warning: {}", path, pos.line, pos.column, message)
+            })
+            .collect(),
+        Err((pos, e)) => vec![format!(
+            "{}:{}:{}: error: {}",
+            path,
+            pos.line,
+            pos.column,
+            errors::describe(&e)
+        )],
+    }
+}
+
+fn describe(warning: &Warning) -> String {
+    match warning {
+        Warning::UnknownFlag(name, flag) => format!("unknown flag '{}' for '{}'", flag, name),
+        Warning::UnknownOption(name, option) => {
+            format!("unknown option '{}' for '{}'", option, name)
+        }
+        Warning::UselessEscape(ch) => format!("'{}' doesn't need to be escaped", ch),
+        Warning::DanglingEscape => "the escape character at the end escapes nothing".to_string(),
+        Warning::EscapeInRawBlock => {
+            "the escape character is taken literally in a raw block".to_string()
+        }
+        Warning::UnescapedSeparator => {
+            "unescaped separator, which is left out of the output".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name check_command;
+        use galvanic_assert::matchers::*;
+
+        use check::*;
+        use pfmt::lint::Kind;
+
+        fn args(s: &str) -> Vec<String> {
+            s.split_whitespace().map(|s| s.to_string()).collect()
+        }
+
+        test options() {
+            let options = parse_args(&args("-d port=int a.txt --declare host=string -")).unwrap();
+            assert_that!(&options.files, eq(vec!["a.txt".to_string(), "-".to_string()]));
+            assert_that!(&options.declarations.get("port"), eq(Some(&Kind::Int)));
+            assert_that!(&options.declarations.get("host"), eq(Some(&Kind::Str)));
+            assert_that!(&parse_args(&args("-d port=long a.txt")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-d port")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-d port=int")).is_err(), eq(true));
+        }
+
+        test report() {
+            let options = parse_args(&args("-d n=uint t")).unwrap();
+            let report = check_template("t", "n: {n:X}\n\\a", &options.declarations);
+            assert_that!(&report, eq(vec![
+                "t:1:2: warning: unescaped separator, which is left out of the output".to_string(),
+                "t:1:4: warning: unknown flag 'X' for 'n'".to_string(),
+                "t:2:1: warning: 'a' doesn't need to be escaped".to_string(),
+            ]));
+            let report = check_template("t", "\n {a", &options.declarations);
+            assert_that!(&report, eq(vec![
+                "t:2:2: error: unterminated placeholder '{a'".to_string(),
+            ]));
+        }
+
+    }
+}
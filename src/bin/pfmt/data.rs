@@ -0,0 +1,133 @@
+//! Turning the data given on the command line into format tables.
+
+use std::collections::HashMap;
+
+use pfmt::tables::Namespace;
+use pfmt::Fmt;
+use serde_json;
+use toml;
+
+/// The values read from a single data source. Objects and arrays become
+/// `Namespace`s, so their members are reached with the dot syntax:
+/// `{servers.0.host}`.
+pub type DataTable = HashMap<String, Box<dyn Fmt>>;
+
+/// Reads a JSON document, whose top level has to be an object.
+pub fn from_json(source: &str) -> Result<DataTable, String> {
+    let value: serde_json::Value = serde_json::from_str(source).map_err(|e| e.to_string())?;
+    match value {
+        serde_json::Value::Object(map) => Ok(map
+            .into_iter()
+            .map(|(key, value)| (key, json_fmt(value)))
+            .collect()),
+        _ => Err("the top level of JSON data must be an object".to_string()),
+    }
+}
+
+/// Reads a TOML document.
+pub fn from_toml(source: &str) -> Result<DataTable, String> {
+    let table: toml::value::Table = toml::from_str(source).map_err(|e| e.to_string())?;
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| (key, toml_fmt(value)))
+        .collect())
+}
+
+/// Splits a `key=value` pair. The value is always a string.
+pub fn parse_pair(pair: &str) -> Result<(String, String), String> {
+    match pair.find('=') {
+        Some(0) | None => Err(format!("expected 'key=value', got '{}'", pair)),
+        Some(pos) => Ok((pair[..pos].to_string(), pair[pos + 1..].to_string())),
+    }
+}
+
+fn json_fmt(value: serde_json::Value) -> Box<dyn Fmt> {
+    use serde_json::Value;
+    match value {
+        Value::Null => Box::new(String::new()),
+        Value::Bool(b) => Box::new(b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Box::new(i)
+            } else if let Some(u) = n.as_u64() {
+                Box::new(u)
+            } else {
+                Box::new(n.as_f64().unwrap_or(0.0))
+            }
+        }
+        Value::String(s) => Box::new(s),
+        Value::Array(items) => {
+            let items: Vec<Box<dyn Fmt>> = items.into_iter().map(json_fmt).collect();
+            Box::new(Namespace(items))
+        }
+        Value::Object(map) => {
+            let table: DataTable = map
+                .into_iter()
+                .map(|(key, value)| (key, json_fmt(value)))
+                .collect();
+            Box::new(Namespace(table))
+        }
+    }
+}
+
+fn toml_fmt(value: toml::Value) -> Box<dyn Fmt> {
+    use toml::Value;
+    match value {
+        Value::String(s) => Box::new(s),
+        Value::Integer(i) => Box::new(i),
+        Value::Float(f) => Box::new(f),
+        Value::Boolean(b) => Box::new(b),
+        Value::Datetime(d) => Box::new(d.to_string()),
+        Value::Array(items) => {
+            let items: Vec<Box<dyn Fmt>> = items.into_iter().map(toml_fmt).collect();
+            Box::new(Namespace(items))
+        }
+        Value::Table(table) => {
+            let table: DataTable = table
+                .into_iter()
+                .map(|(key, value)| (key, toml_fmt(value)))
+                .collect();
+            Box::new(Namespace(table))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name data;
+        use galvanic_assert::matchers::*;
+
+        use data::*;
+        use pfmt::{FormatTable, FormattingError};
+
+        test json() {
+            let table = from_json(r#"{"s": "a", "i": 10, "f": 0.5, "b": true, "n": null,
+                                      "list": [1, {"x": "y"}], "obj": {"k": "v"}}"#).unwrap();
+            let s = table.format("{s} {i:x} {f} {b:y} [{n}] {list.0} {list.1.x} {obj.k}");
+            assert_that!(&s, eq(Ok("a a 0.5 yes [] 1 y v".to_string())));
+            let s = table.format("{obj.missing}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("obj.missing".to_string(), vec![]))));
+        }
+
+        test json_errors() {
+            assert_that!(&from_json("[1]").is_err(), eq(true));
+            assert_that!(&from_json("{").is_err(), eq(true));
+        }
+
+        test toml() {
+            let table = from_toml("name = \"x\"\nport = 80\n[db]\nhosts = [\"a\", \"b\"]\n")
+                .unwrap();
+            let s = table.format("{name}\\:{port} {db.hosts.1}");
+            assert_that!(&s, eq(Ok("x:80 b".to_string())));
+        }
+
+        test pairs() {
+            assert_that!(&parse_pair("a=b=c"), eq(Ok(("a".to_string(), "b=c".to_string()))));
+            assert_that!(&parse_pair("a="), eq(Ok(("a".to_string(), "".to_string()))));
+            assert_that!(&parse_pair("=b").is_err(), eq(true));
+            assert_that!(&parse_pair("ab").is_err(), eq(true));
+        }
+
+    }
+}
@@ -0,0 +1,116 @@
+//! Describing formatting errors to the people running `pfmt`.
+
+use pfmt::FormattingError;
+
+/// The longest part of a template quoted in an error message.
+const MAX_EXCERPT_LEN: usize = 40;
+
+/// Describes an error in a sentence fit for the command line.
+pub fn describe(error: &FormattingError) -> String {
+    match error {
+        FormattingError::EmptyName(s) => format!("empty name in '{}'", excerpt(s)),
+        FormattingError::UnterminatedArgumentList(s) => {
+            format!("unterminated argument list in '{}'", excerpt(s))
+        }
+        FormattingError::UnterminatedPlaceholder(s) => {
+            format!("unterminated placeholder '{}'", excerpt(s))
+        }
+        FormattingError::InvalidExpression(s) => format!("invalid expression in '{}'", excerpt(s)),
+        FormattingError::UnmatchedClosingDelimiter(_) => {
+            "unmatched closing delimiter, which has to be escaped".to_string()
+        }
+        FormattingError::UnknownFlag(flag) => format!("unknown flag '{}'", flag),
+        FormattingError::UnknownOption(option, close) => {
+            format!("unknown option '{}'{}", option, did_you_mean(close))
+        }
+        FormattingError::InvalidOptionValue(option, value) => {
+            format!("invalid value '{}' for option '{}'", value, option)
+        }
+        FormattingError::NamespaceOnlyFmt(name) => {
+            format!(
+                "'{}' only holds other values, and can't be used by itself",
+                name
+            )
+        }
+        FormattingError::InvalidArgumentCount(name, count) => {
+            format!("'{}' can't take {} argument(s)", name, count)
+        }
+        FormattingError::InvalidArgument(name, arg) => {
+            format!("invalid argument '{}' for '{}'", arg, name)
+        }
+        FormattingError::UnknownFilter(name) => format!("unknown filter '{}'", name),
+        FormattingError::FilterFailed(name, message) => {
+            format!("filter '{}' failed: {}", name, message)
+        }
+        FormattingError::UnknownTemplate(name) => format!("unknown template '{}'", name),
+        FormattingError::IncludeDepthExceeded(name) => {
+            format!("includes nested too deep at template '{}'", name)
+        }
+        FormattingError::NotANumber(value) => format!("'{}' is not a number", value),
+        FormattingError::TypeMismatch(expression, value) => {
+            format!("'{}' has the wrong type for '{}'", value, expression)
+        }
+        FormattingError::DivisionByZero(expression) => {
+            format!("division by zero in '{}'", expression)
+        }
+        FormattingError::UnknownFmt(name, close) => {
+            format!("unknown name '{}'{}", name, did_you_mean(close))
+        }
+    }
+}
+
+/// The start of the template the error was found at, up to the end of its
+/// line, shortened if it's too long.
+fn excerpt(s: &str) -> String {
+    let line = s.lines().next().unwrap_or("");
+    if line.chars().count() > MAX_EXCERPT_LEN {
+        let short: String = line.chars().take(MAX_EXCERPT_LEN).collect();
+        format!("{}...", short)
+    } else {
+        line.to_string()
+    }
+}
+
+fn did_you_mean(close: &[String]) -> String {
+    let quoted: Vec<String> = close.iter().map(|s| format!("'{}'", s)).collect();
+    match quoted.split_last() {
+        None => String::new(),
+        Some((last, [])) => format!(" (did you mean {}?)", last),
+        Some((last, rest)) => format!(" (did you mean {} or {}?)", rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name descriptions;
+        use galvanic_assert::matchers::*;
+
+        use errors::describe;
+        use pfmt::FormattingError;
+
+        fn strings(names: &[&str]) -> Vec<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        }
+
+        test suggestions() {
+            let e = FormattingError::UnknownFmt("user".to_string(), vec![]);
+            assert_that!(&describe(&e), eq("unknown name 'user'".to_string()));
+            let e = FormattingError::UnknownFmt("usr".to_string(), strings(&["user"]));
+            assert_that!(&describe(&e), eq("unknown name 'usr' (did you mean 'user'?)".to_string()));
+            let e = FormattingError::UnknownOption("wdth".to_string(),
+                                                   strings(&["width", "wrap", "with"]));
+            assert_that!(&describe(&e),
+                         eq("unknown option 'wdth' (did you mean 'width', 'wrap' or 'with'?)".to_string()));
+        }
+
+        test excerpts() {
+            let e = FormattingError::UnterminatedPlaceholder("{a\nb".to_string());
+            assert_that!(&describe(&e), eq("unterminated placeholder '{a'".to_string()));
+            let e = FormattingError::EmptyName(format!("{{:x}}{}", "y".repeat(50)));
+            assert_that!(&describe(&e),
+                         eq(format!("empty name in '{{:x}}{}...'", "y".repeat(36))));
+        }
+
+    }
+}
@@ -0,0 +1,105 @@
+//! Line mode: splitting lines of input into fields, awk-style.
+
+use pfmt::Fmt;
+use regex::Regex;
+
+use data::DataTable;
+
+/// How a line is split into fields.
+#[derive(Debug)]
+pub enum Splitter {
+    /// Fields are separated by runs of whitespace.
+    Whitespace,
+    /// Fields are separated by the given string.
+    Separator(String),
+    /// Fields are the groups of the regex, which has to match the line.
+    /// Named groups are also available by their names.
+    Regex(Regex),
+}
+
+/// The table a single line is formatted with: the named values, and then
+/// the fields by their positions.
+pub type Record = (DataTable, Vec<Box<dyn Fmt>>);
+
+impl Splitter {
+    /// Splits a line into a record, or returns `None` if it's to be skipped,
+    /// which is the case for the lines a regex doesn't match. Besides the
+    /// fields, a record has `line` (the whole line), `nr` (its number,
+    /// starting from 1) and `nf` (the number of fields).
+    pub fn record(&self, line: &str, number: usize) -> Option<Record> {
+        let mut named = DataTable::new();
+        let fields: Vec<String> = match self {
+            Splitter::Whitespace => line.split_whitespace().map(|s| s.to_string()).collect(),
+            Splitter::Separator(sep) => line.split(sep.as_str()).map(|s| s.to_string()).collect(),
+            Splitter::Regex(regex) => {
+                let captures = regex.captures(line)?;
+                for name in regex.capture_names().flatten() {
+                    let value = captures.name(name).map_or("", |m| m.as_str());
+                    named.insert(name.to_string(), Box::new(value.to_string()));
+                }
+                captures
+                    .iter()
+                    .skip(1)
+                    .map(|m| m.map_or("", |m| m.as_str()).to_string())
+                    .collect()
+            }
+        };
+        named
+            .entry("line".to_string())
+            .or_insert_with(|| Box::new(line.to_string()));
+        named
+            .entry("nr".to_string())
+            .or_insert_with(|| Box::new(number));
+        named
+            .entry("nf".to_string())
+            .or_insert_with(|| Box::new(fields.len()));
+        let fields = fields
+            .into_iter()
+            .map(|field| Box::new(field) as Box<dyn Fmt>)
+            .collect();
+        Some((named, fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name splitting;
+        use galvanic_assert::matchers::*;
+        use regex::Regex;
+
+        use lines::Splitter;
+        use pfmt::{FormatTable, FormattingError};
+
+        test whitespace() {
+            let record = Splitter::Whitespace.record("  GET /index.html  200 ", 3).unwrap();
+            let s = record.format("{nr}/{nf}\\: {1} {2} {0::width=l4}|");
+            assert_that!(&s, eq(Ok("3/3: /index.html 200 GET |".to_string())));
+            let s = record.format("{3}");
+            let suggestions = vec!["0".to_string(), "1".to_string(), "2".to_string()];
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("3".to_string(), suggestions))));
+        }
+
+        test separator() {
+            let record = Splitter::Separator(",".to_string()).record("a,,c", 1).unwrap();
+            let s = record.format("[{0}][{1}][{2}] {nf}");
+            assert_that!(&s, eq(Ok("[a][][c] 3".to_string())));
+        }
+
+        test regex() {
+            let regex = Regex::new(r"^(?P<method>\w+) (\S+)(?: (\d+))?$").unwrap();
+            let splitter = Splitter::Regex(regex);
+            let record = splitter.record("GET /a", 1).unwrap();
+            let s = record.format("{method} {1} [{2}] {nf} {line}");
+            assert_that!(&s, eq(Ok("GET /a [] 3 GET /a".to_string())));
+            assert_that!(&splitter.record("nope", 2).is_none(), eq(true));
+        }
+
+        test groups_shadow_builtins() {
+            let splitter = Splitter::Regex(Regex::new(r"(?P<line>\d+)").unwrap());
+            let record = splitter.record("at 12", 1).unwrap();
+            assert_that!(&record.format("{line}"), eq(Ok("12".to_string())));
+        }
+
+    }
+}
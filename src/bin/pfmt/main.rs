@@ -0,0 +1,461 @@
+//! `pfmt`, a command line front end to the library. Renders a template with
+//! the data taken from JSON and TOML documents, `key=value` pairs and the
+//! environment, so that shell scripts can use the same format strings as
+//! the programs built on the library. `pfmt check` checks templates for
+//! mistakes instead.
+
+#[cfg(test)]
+#[macro_use]
+extern crate galvanic_assert;
+#[cfg(test)]
+#[macro_use]
+extern crate galvanic_test;
+
+extern crate pfmt;
+extern crate regex;
+extern crate serde_json;
+extern crate toml;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::process;
+
+use pfmt::escape::Escaper;
+use pfmt::tables::{EnvTable, Scopes};
+use pfmt::templates::TemplateRegistry;
+use pfmt::{functions, Context, FormatTable};
+use regex::Regex;
+
+use lines::Splitter;
+
+mod check;
+mod data;
+mod errors;
+mod lines;
+
+const USAGE: &str = "\
+Usage: pfmt [OPTIONS] TEMPLATE
+       pfmt [OPTIONS] -f FILE
+       pfmt check [-d NAME=TYPE]... FILE...
+
+Renders a template with the given data and prints the result. In line mode,
+renders the template once for every line of the standard input instead.
+
+Options:
+  -f, --file FILE        read the template from FILE ('-' for stdin)
+  -j, --json FILE        take data from a JSON document ('-' for stdin)
+  -t, --toml FILE        take data from a TOML document ('-' for stdin)
+  -s, --set KEY=VALUE    set a single string value (KEY cannot contain dots)
+  -e, --env              make the environment variables available
+      --env-prefix PREFIX
+                         only make the variables starting with PREFIX
+                         available (implies --env)
+      --escape ESCAPER   escape every placeholder with one of html, json,
+                         shell, url or csv
+      --strict           fail on the flags and options that are unknown to
+                         the type of a value
+  -l, --lines            turn on line mode, splitting lines at whitespace
+  -F, --field-separator SEPARATOR
+                         split lines at SEPARATOR (implies --lines)
+  -r, --regex REGEX      take the groups of REGEX as the fields, skipping
+                         the lines it doesn't match (implies --lines)
+  -h, --help             print this message
+
+Data sources given later override the earlier ones. The environment comes
+before all of them, and the functions (join, if, ...) before that.
+
+In line mode, the fields are available as {0}, {1} and so on, the named
+groups of the regex by their names, the whole line as {line}, its number
+as {nr} and the number of fields as {nf}. These override all the data.
+As the lines are read from stdin, the template and the data can't be.
+
+The check command reports the syntax errors in the templates, along with
+the flags and options unknown to the declared TYPE of a placeholder (bool,
+char, float, int, uint or string), the escapes that change nothing and the
+separators that are left out of the output. It exits with 1 if it finds
+any of these.";
+
+/// Where the template comes from.
+#[derive(Debug, PartialEq)]
+enum Template {
+    Inline(String),
+    File(String),
+}
+
+/// A source of data, in the order given on the command line.
+#[derive(Debug, PartialEq)]
+enum DataSource {
+    Json(String),
+    Toml(String),
+    Pair(String, String),
+}
+
+/// Everything the command line asks for.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    template: Option<Template>,
+    data: Vec<DataSource>,
+    env: bool,
+    env_prefix: Option<String>,
+    escaper: Option<Escaper>,
+    strict: bool,
+    lines: bool,
+    field_separator: Option<String>,
+    regex: Option<String>,
+    help: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-f" | "--file" => set_template(&mut options, Template::File(value(arg)?))?,
+            "-j" | "--json" => options.data.push(DataSource::Json(value(arg)?)),
+            "-t" | "--toml" => options.data.push(DataSource::Toml(value(arg)?)),
+            "-s" | "--set" => {
+                let (key, val) = data::parse_pair(&value(arg)?)?;
+                if key.contains('.') {
+                    return Err(format!(
+                        "'{}' cannot be set with --set, use --json or --toml for nested data",
+                        key
+                    ));
+                }
+                options.data.push(DataSource::Pair(key, val));
+            }
+            "-e" | "--env" => options.env = true,
+            "--env-prefix" => {
+                options.env = true;
+                options.env_prefix = Some(value(arg)?);
+            }
+            "--escape" => {
+                let name = value(arg)?;
+                let escaper = Escaper::from_name(&name)
+                    .ok_or_else(|| format!("unknown escaper '{}'", name))?;
+                options.escaper = Some(escaper);
+            }
+            "--strict" => options.strict = true,
+            "-l" | "--lines" => options.lines = true,
+            "-F" | "--field-separator" => {
+                options.lines = true;
+                options.field_separator = Some(value(arg)?);
+            }
+            "-r" | "--regex" => {
+                options.lines = true;
+                options.regex = Some(value(arg)?);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option '{}'", arg));
+            }
+            _ => set_template(&mut options, Template::Inline(arg.clone()))?,
+        }
+    }
+    if options.template.is_none() && !options.help {
+        return Err("no template given".to_string());
+    }
+    if options.field_separator.is_some() && options.regex.is_some() {
+        return Err("a field separator and a regex cannot be used together".to_string());
+    }
+    let stdin_readers = stdin_readers(&options);
+    if options.lines && stdin_readers > 0 {
+        return Err(
+            "in line mode, the template and the data cannot be read from stdin".to_string(),
+        );
+    }
+    if stdin_readers > 1 {
+        return Err("only one of the template and the data can be read from stdin".to_string());
+    }
+    Ok(options)
+}
+
+/// How many of the template and the data sources are read from stdin.
+fn stdin_readers(options: &Options) -> usize {
+    let template = match &options.template {
+        Some(Template::File(path)) => path == "-",
+        _ => false,
+    };
+    let data = options.data.iter().filter(|source| match source {
+        DataSource::Json(path) | DataSource::Toml(path) => path == "-",
+        DataSource::Pair(..) => false,
+    });
+    data.count() + template as usize
+}
+
+fn set_template(options: &mut Options, template: Template) -> Result<(), String> {
+    if options.template.is_some() {
+        return Err("only one template can be given".to_string());
+    }
+    options.template = Some(template);
+    Ok(())
+}
+
+/// Reads a file, or the standard input if the path is `-`.
+fn read_source(path: &str) -> Result<String, String> {
+    let res = if path == "-" {
+        let mut s = String::new();
+        io::stdin().read_to_string(&mut s).map(|_| s)
+    } else {
+        fs::read_to_string(path)
+    };
+    res.map_err(|e| format!("{}: {}", path, e))
+}
+
+fn load_template(options: &Options) -> Result<String, String> {
+    match &options.template {
+        Some(Template::Inline(s)) => Ok(s.clone()),
+        Some(Template::File(path)) => read_source(path),
+        None => Err("no template given".to_string()),
+    }
+}
+
+/// Builds the table of the functions, the environment and the data.
+fn data_scopes(options: &Options) -> Result<Scopes<'static>, String> {
+    let mut scopes = Scopes::new();
+    scopes.push(functions::table());
+    if options.env {
+        let mut env_table = EnvTable::new();
+        if let Some(prefix) = &options.env_prefix {
+            env_table.set_prefix(prefix);
+        }
+        scopes.push(env_table);
+    }
+    for source in options.data.iter() {
+        match source {
+            DataSource::Json(path) => {
+                let table =
+                    data::from_json(&read_source(path)?).map_err(|e| format!("{}: {}", path, e))?;
+                scopes.push(table);
+            }
+            DataSource::Toml(path) => {
+                let table =
+                    data::from_toml(&read_source(path)?).map_err(|e| format!("{}: {}", path, e))?;
+                scopes.push(table);
+            }
+            DataSource::Pair(key, value) => {
+                let mut table = data::DataTable::new();
+                table.insert(key.clone(), Box::new(value.clone()));
+                scopes.push(table);
+            }
+        }
+    }
+    Ok(scopes)
+}
+
+fn render(options: &Options) -> Result<String, String> {
+    let template = load_template(options)?;
+    let scopes = data_scopes(options)?;
+    let context = Context {
+        escaper: options.escaper,
+        strict: options.strict,
+        ..Context::default()
+    };
+    scopes
+        .format_with(&template, &context)
+        .map_err(|e| errors::describe(&e))
+}
+
+/// Renders the template for every line of the input.
+fn render_lines<R: BufRead, W: Write>(
+    options: &Options,
+    input: R,
+    mut output: W,
+) -> Result<(), String> {
+    let splitter = match (&options.field_separator, &options.regex) {
+        (Some(sep), _) => Splitter::Separator(sep.clone()),
+        (_, Some(regex)) => Splitter::Regex(Regex::new(regex).map_err(|e| e.to_string())?),
+        _ => Splitter::Whitespace,
+    };
+    // The template is parsed once, and included for every line.
+    let mut templates = TemplateRegistry::new();
+    templates
+        .register("line", &load_template(options)?)
+        .map_err(|e| errors::describe(&e))?;
+    let context = Context {
+        escaper: options.escaper,
+        templates: Some(&templates),
+        strict: options.strict,
+        ..Context::default()
+    };
+    let mut scopes = data_scopes(options)?;
+    for (i, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let record = match splitter.record(&line, i + 1) {
+            Some(record) => record,
+            None => continue,
+        };
+        scopes.push(record);
+        let res = scopes.format_with("{>line}", &context);
+        scopes.pop();
+        let s = res.map_err(|e| format!("line {}: {}", i + 1, errors::describe(&e)))?;
+        writeln!(output, "{}", s).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Runs `pfmt check`, returning whether there were no problems.
+fn run_check(args: &[String]) -> bool {
+    let options = match check::parse_args(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("pfmt: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return true;
+    }
+    let mut clean = true;
+    for path in options.files.iter() {
+        let report = match read_source(path) {
+            Ok(template) => check::check_template(path, &template, &options.declarations),
+            Err(e) => vec![e],
+        };
+        for line in report.iter() {
+            eprintln!("{}", line);
+        }
+        clean = clean && report.is_empty();
+    }
+    clean
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("check") {
+        let clean = run_check(&args[1..]);
+        process::exit(if clean { 0 } else { 1 });
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("pfmt: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if options.lines {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        if let Err(e) = render_lines(&options, stdin.lock(), stdout.lock()) {
+            eprintln!("pfmt: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    match render(&options) {
+        Ok(mut s) => {
+            if !s.ends_with('\n') {
+                s.push('\n');
+            }
+            let _ = io::stdout().write_all(s.as_bytes());
+        }
+        Err(e) => {
+            eprintln!("pfmt: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name command_line;
+        use galvanic_assert::matchers::*;
+
+        use pfmt::escape::Escaper;
+        use {parse_args, render, render_lines, DataSource, Options, Template};
+
+        fn args(s: &str) -> Vec<String> {
+            s.split_whitespace().map(|s| s.to_string()).collect()
+        }
+
+        test options() {
+            let options = parse_args(&args("-s a=1 --json x.json -t y.toml --escape html -e {a}"));
+            assert_that!(&options, eq(Ok(Options {
+                template: Some(Template::Inline("{a}".to_string())),
+                data: vec![DataSource::Pair("a".to_string(), "1".to_string()),
+                           DataSource::Json("x.json".to_string()),
+                           DataSource::Toml("y.toml".to_string())],
+                env: true,
+                env_prefix: None,
+                escaper: Some(Escaper::Html),
+                strict: false,
+                lines: false,
+                field_separator: None,
+                regex: None,
+                help: false,
+            })));
+        }
+
+        test bad_options() {
+            assert_that!(&parse_args(&args("-s")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-x {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("{a} {b}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-f t.txt {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("--escape xml {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-s a=1")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-s a.b=1 {a.b}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-h")).map(|o| o.help), eq(Ok(true)));
+            assert_that!(&parse_args(&args("-F , -r x {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-l -f - ")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-l -j - {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-F , -t - {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-f - -j -")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-j - -t - {a}")).is_err(), eq(true));
+            assert_that!(&parse_args(&args("-j - -t a.toml {a}")).is_ok(), eq(true));
+        }
+
+        test later_sources_win() {
+            let options = parse_args(&args("-s a=1 -s b=<2> -s a=3 --escape html {a}{b}{repeat{x:2}}"))
+                .unwrap();
+            assert_that!(&render(&options), eq(Ok("3&lt;2&gt;xx".to_string())));
+        }
+
+        test errors() {
+            let options = parse_args(&args("{a}")).unwrap();
+            assert_that!(&render(&options), eq(Err("unknown name 'a'".to_string())));
+            let options = parse_args(&args("-j /nonexistent/pfmt.json {a}")).unwrap();
+            assert_that!(&render(&options).is_err(), eq(true));
+            let options = parse_args(&args("--strict {repeat{a:2}:x}")).unwrap();
+            assert_that!(&render(&options), eq(Err("unknown flag 'x'".to_string())));
+        }
+
+        test lines() {
+            let options = parse_args(&args("-F : -s sep=| {nr}{sep}{1}{sep}{0::width=r3}")).unwrap();
+            let mut output = Vec::new();
+            let res = render_lines(&options, &b"a:b\nc:d:e\n"[..], &mut output);
+            assert_that!(&res, eq(Ok(())));
+            let output = String::from_utf8(output).unwrap();
+            assert_that!(&output.as_str(), eq("1|b|  a\n2|d|  c\n"));
+        }
+
+        test line_regex() {
+            let options = parse_args(&args("-r ^(?P<k>\\w+)=(\\d+)$ --escape html {k}<{1}>")).unwrap();
+            let mut output = Vec::new();
+            let res = render_lines(&options, &b"a=1\n# skip\nb=2"[..], &mut output);
+            assert_that!(&res, eq(Ok(())));
+            let output = String::from_utf8(output).unwrap();
+            assert_that!(&output.as_str(), eq("a<1>\nb<2>\n"));
+        }
+
+        test line_errors() {
+            let options = parse_args(&args("-l {0}{1}")).unwrap();
+            let mut output = Vec::new();
+            let res = render_lines(&options, &b"a b\nc"[..], &mut output);
+            assert_that!(&res, eq(Err("line 2: unknown name '1' (did you mean '0'?)".to_string())));
+            let options = parse_args(&args("-r ( {0}")).unwrap();
+            assert_that!(&render_lines(&options, &b""[..], Vec::new()).is_err(), eq(true));
+        }
+
+    }
+}
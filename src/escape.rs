@@ -0,0 +1,189 @@
+//! Escaping of formatted values for different output languages.
+//!
+//! Escaping is applied to the output of a placeholder after its `Fmt` has
+//! produced it, so it works for any `Fmt`, including ones the library knows
+//! nothing about. Literal parts of a format string are never escaped.
+
+/// A built-in escaping scheme.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Escaper {
+    /// Replaces `&`, `<`, `>`, `"` and `'` with HTML entities. Safe for both
+    /// element content and quoted attribute values.
+    Html,
+    /// Escapes the value so that it can be placed between double quotes in a
+    /// JSON document. The quotes themselves are not added.
+    Json,
+    /// Wraps the value in single quotes, so that a POSIX shell treats it as a
+    /// single word with no expansions. Embedded single quotes are written as
+    /// `'\''`.
+    Shell,
+    /// Percent-encodes everything except the unreserved characters of RFC
+    /// 3986 (ASCII letters, digits, `-`, `.`, `_` and `~`).
+    Url,
+    /// Produces a CSV field as described by RFC 4180: if the value contains a
+    /// comma, a double quote or a line break, it is wrapped in double quotes
+    /// and the quotes inside are doubled. Other values are left alone.
+    Csv,
+}
+
+impl Escaper {
+    /// Looks up an escaper by the name used in format strings: `html`, `json`,
+    /// `shell`, `url` or `csv`.
+    pub fn from_name(name: &str) -> Option<Escaper> {
+        match name {
+            "html" => Some(Escaper::Html),
+            "json" => Some(Escaper::Json),
+            "shell" => Some(Escaper::Shell),
+            "url" => Some(Escaper::Url),
+            "csv" => Some(Escaper::Csv),
+            _ => None,
+        }
+    }
+
+    /// The name of this escaper, as used in format strings.
+    pub fn name(self) -> &'static str {
+        match self {
+            Escaper::Html => "html",
+            Escaper::Json => "json",
+            Escaper::Shell => "shell",
+            Escaper::Url => "url",
+            Escaper::Csv => "csv",
+        }
+    }
+
+    /// Escapes a string according to this scheme.
+    pub fn escape(self, s: &str) -> String {
+        match self {
+            Escaper::Html => escape_html(s),
+            Escaper::Json => escape_json(s),
+            Escaper::Shell => escape_shell(s),
+            Escaper::Url => escape_url(s),
+            Escaper::Csv => escape_csv(s),
+        }
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+fn escape_json(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            '\u{8}' => res.push_str("\\b"),
+            '\u{c}' => res.push_str("\\f"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+fn escape_shell(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('\'');
+    for ch in s.chars() {
+        if ch == '\'' {
+            res.push_str("'\\''");
+        } else {
+            res.push(ch);
+        }
+    }
+    res.push('\'');
+    res
+}
+
+fn escape_url(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                res.push(byte as char)
+            }
+            _ => res.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    res
+}
+
+fn escape_csv(s: &str) -> String {
+    if !s.contains(&[',', '"', '\n', '\r'][..]) {
+        return s.to_string();
+    }
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for ch in s.chars() {
+        if ch == '"' {
+            res.push('"');
+        }
+        res.push(ch);
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name escapers;
+        use galvanic_assert::matchers::*;
+
+        use escape::Escaper;
+
+        test html() {
+            let s = Escaper::Html.escape("<a href=\"x\">Tom & Jerry's</a>");
+            assert_that!(&s.as_str(),
+                         eq("&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"));
+        }
+
+        test json() {
+            let s = Escaper::Json.escape("say \"hi\"\\\n\t\u{1}");
+            assert_that!(&s.as_str(), eq("say \\\"hi\\\"\\\\\\n\\t\\u0001"));
+        }
+
+        test shell() {
+            let s = Escaper::Shell.escape("it's $HOME");
+            assert_that!(&s.as_str(), eq("'it'\\''s $HOME'"));
+        }
+
+        test url() {
+            let s = Escaper::Url.escape("a b/c?d=é~");
+            assert_that!(&s.as_str(), eq("a%20b%2Fc%3Fd%3D%C3%A9~"));
+        }
+
+        test csv_plain() {
+            let s = Escaper::Csv.escape("plain value");
+            assert_that!(&s.as_str(), eq("plain value"));
+        }
+
+        test csv_quoted() {
+            let s = Escaper::Csv.escape("a, \"b\"");
+            assert_that!(&s.as_str(), eq("\"a, \"\"b\"\"\""));
+        }
+
+        test names() {
+            for e in &[Escaper::Html, Escaper::Json, Escaper::Shell, Escaper::Url, Escaper::Csv] {
+                assert_that!(&Escaper::from_name(e.name()), eq(Some(*e)));
+            }
+            assert_that!(&Escaper::from_name("xml"), eq(None));
+        }
+
+    }
+}
@@ -0,0 +1,476 @@
+//! Arithmetic, comparison and boolean expressions used in place of a name in
+//! a placeholder, like `{=used / total * 100 ::prec=1}`.
+//!
+//! Operators, from the loosest binding to the tightest:
+//! * `or`;
+//! * `and`;
+//! * `not`;
+//! * `==`, `!=`, `<`, `<=`, `>` and `>=`, which don't chain;
+//! * `+` and `-`;
+//! * `*`, `/` and `%`;
+//! * unary `-`.
+//!
+//! Operands are numbers, `true`, `false`, parenthesized expressions and
+//! names of `Fmt`s, which are formatted without any flags or options and
+//! then read back as integers, floats or booleans.
+
+use std::cmp::Ordering;
+
+use FormattingError;
+
+/// Characters that end a name in an expression.
+const OPERATOR_CHARS: &[char] = &['+', '-', '*', '/', '%', '<', '>', '=', '!', '(', ')'];
+
+/// A parsed expression, along with its source, which is used in errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub source: String,
+    pub root: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Scalar),
+    Name(Vec<String>),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+/// The value of an expression or of one of its operands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Scalar {
+    /// Reads a formatted value back.
+    fn from_formatted(s: &str) -> Option<Scalar> {
+        let s = s.trim();
+        match s {
+            "true" => Some(Scalar::Bool(true)),
+            "false" => Some(Scalar::Bool(false)),
+            _ => s
+                .parse()
+                .map(Scalar::Int)
+                .or_else(|_| s.parse().map(Scalar::Float))
+                .ok(),
+        }
+    }
+
+    fn to_float(self) -> Option<f64> {
+        match self {
+            Scalar::Int(i) => Some(i as f64),
+            Scalar::Float(f) => Some(f),
+            Scalar::Bool(_) => None,
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Scalar::Int(i) => i.to_string(),
+            Scalar::Float(f) => f.to_string(),
+            Scalar::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+/// Parses an expression. Returns `None` if it's malformed.
+pub fn parse_expression(source: &str) -> Option<Expression> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let root = parser.or()?;
+    if parser.pos != parser.tokens.len() {
+        return None;
+    }
+    Some(Expression {
+        source: source.trim().to_string(),
+        root,
+    })
+}
+
+/* ---------- parsing ---------- */
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Op(&'static str),
+    OpenParen,
+    CloseParen,
+}
+
+fn tokenize(source: &str) -> Option<Vec<Token>> {
+    const OPS: &[&str] = &["==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%"];
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while !rest.is_empty() {
+        if rest.starts_with('(') {
+            tokens.push(Token::OpenParen);
+            rest = &rest[1..];
+        } else if rest.starts_with(')') {
+            tokens.push(Token::CloseParen);
+            rest = &rest[1..];
+        } else if let Some(op) = OPS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || OPERATOR_CHARS.contains(&c))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                // A lone `=` or `!`.
+                return None;
+            }
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w == word => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn eat_op(&mut self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        if let Some(Token::Op(op)) = self.peek() {
+            if let Some((_, res)) = ops.iter().find(|(o, _)| o == op) {
+                self.pos += 1;
+                return Some(*res);
+            }
+        }
+        None
+    }
+
+    fn or(&mut self) -> Option<Expr> {
+        let mut lhs = self.and()?;
+        while self.eat_word("or") {
+            let rhs = self.and()?;
+            lhs = Expr::Binary(BinaryOp::Or, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn and(&mut self) -> Option<Expr> {
+        let mut lhs = self.not()?;
+        while self.eat_word("and") {
+            let rhs = self.not()?;
+            lhs = Expr::Binary(BinaryOp::And, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn not(&mut self) -> Option<Expr> {
+        if self.eat_word("not") {
+            Some(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn comparison(&mut self) -> Option<Expr> {
+        const OPS: &[(&str, BinaryOp)] = &[
+            ("==", BinaryOp::Equal),
+            ("!=", BinaryOp::NotEqual),
+            ("<", BinaryOp::Less),
+            ("<=", BinaryOp::LessOrEqual),
+            (">", BinaryOp::Greater),
+            (">=", BinaryOp::GreaterOrEqual),
+        ];
+        let lhs = self.sum()?;
+        match self.eat_op(OPS) {
+            Some(op) => {
+                let rhs = self.sum()?;
+                Some(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+            }
+            None => Some(lhs),
+        }
+    }
+
+    fn sum(&mut self) -> Option<Expr> {
+        const OPS: &[(&str, BinaryOp)] = &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)];
+        let mut lhs = self.product()?;
+        while let Some(op) = self.eat_op(OPS) {
+            let rhs = self.product()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn product(&mut self) -> Option<Expr> {
+        const OPS: &[(&str, BinaryOp)] = &[
+            ("*", BinaryOp::Multiply),
+            ("/", BinaryOp::Divide),
+            ("%", BinaryOp::Remainder),
+        ];
+        let mut lhs = self.negation()?;
+        while let Some(op) = self.eat_op(OPS) {
+            let rhs = self.negation()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn negation(&mut self) -> Option<Expr> {
+        if self.peek() == Some(&Token::Op("-")) {
+            self.pos += 1;
+            Some(Expr::Negate(Box::new(self.negation()?)))
+        } else {
+            self.operand()
+        }
+    }
+
+    fn operand(&mut self) -> Option<Expr> {
+        let token = self.peek()?.clone();
+        self.pos += 1;
+        match token {
+            Token::OpenParen => {
+                let expr = self.or()?;
+                if self.peek() != Some(&Token::CloseParen) {
+                    return None;
+                }
+                self.pos += 1;
+                Some(expr)
+            }
+            Token::Word(word) => match word.as_str() {
+                "and" | "or" | "not" => None,
+                "true" => Some(Expr::Literal(Scalar::Bool(true))),
+                "false" => Some(Expr::Literal(Scalar::Bool(false))),
+                _ if word.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+                    Scalar::from_formatted(&word).map(Expr::Literal)
+                }
+                _ => {
+                    let name: Vec<String> = word.split('.').map(|s| s.to_string()).collect();
+                    if name.iter().any(|segment| segment.is_empty()) {
+                        None
+                    } else {
+                        Some(Expr::Name(name))
+                    }
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+/* ---------- evaluation ---------- */
+
+impl Expression {
+    /// Evaluates the expression. Names are formatted with `lookup`.
+    pub fn evaluate<F>(&self, lookup: &F) -> Result<Scalar, FormattingError>
+    where
+        F: Fn(&[String]) -> Result<String, FormattingError>,
+    {
+        self.eval(&self.root, lookup)
+    }
+
+    fn eval<F>(&self, expr: &Expr, lookup: &F) -> Result<Scalar, FormattingError>
+    where
+        F: Fn(&[String]) -> Result<String, FormattingError>,
+    {
+        match expr {
+            Expr::Literal(value) => Ok(*value),
+            Expr::Name(name) => {
+                let s = lookup(name)?;
+                Scalar::from_formatted(&s).ok_or_else(|| self.mismatch(&s))
+            }
+            Expr::Negate(expr) => match self.eval(expr, lookup)? {
+                Scalar::Int(i) => Ok(i
+                    .checked_neg()
+                    .map(Scalar::Int)
+                    .unwrap_or_else(|| Scalar::Float(-(i as f64)))),
+                Scalar::Float(f) => Ok(Scalar::Float(-f)),
+                value => Err(self.mismatch(&value.describe())),
+            },
+            Expr::Not(expr) => Ok(Scalar::Bool(!self.boolean(expr, lookup)?)),
+            Expr::Binary(BinaryOp::And, lhs, rhs) => {
+                Ok(Scalar::Bool(self.boolean(lhs, lookup)? && self.boolean(rhs, lookup)?))
+            }
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => {
+                Ok(Scalar::Bool(self.boolean(lhs, lookup)? || self.boolean(rhs, lookup)?))
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, lookup)?;
+                let rhs = self.eval(rhs, lookup)?;
+                self.binary(*op, lhs, rhs)
+            }
+        }
+    }
+
+    fn boolean<F>(&self, expr: &Expr, lookup: &F) -> Result<bool, FormattingError>
+    where
+        F: Fn(&[String]) -> Result<String, FormattingError>,
+    {
+        match self.eval(expr, lookup)? {
+            Scalar::Bool(b) => Ok(b),
+            value => Err(self.mismatch(&value.describe())),
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, lhs: Scalar, rhs: Scalar) -> Result<Scalar, FormattingError> {
+        match (op, lhs, rhs) {
+            (BinaryOp::Equal, Scalar::Bool(a), Scalar::Bool(b)) => return Ok(Scalar::Bool(a == b)),
+            (BinaryOp::NotEqual, Scalar::Bool(a), Scalar::Bool(b)) => return Ok(Scalar::Bool(a != b)),
+            _ => (),
+        }
+        let (a, b) = match (lhs.to_float(), rhs.to_float()) {
+            (Some(a), Some(b)) => (a, b),
+            (None, _) => return Err(self.mismatch(&lhs.describe())),
+            (_, None) => return Err(self.mismatch(&rhs.describe())),
+        };
+        let ints = match (lhs, rhs) {
+            (Scalar::Int(a), Scalar::Int(b)) => Some((a, b)),
+            _ => None,
+        };
+        let ordering = match ints {
+            Some((a, b)) => Some(a.cmp(&b)),
+            None => a.partial_cmp(&b),
+        };
+        let res = match op {
+            BinaryOp::Add => int_or_float(ints.and_then(|(a, b)| a.checked_add(b)), a + b),
+            BinaryOp::Subtract => int_or_float(ints.and_then(|(a, b)| a.checked_sub(b)), a - b),
+            BinaryOp::Multiply => int_or_float(ints.and_then(|(a, b)| a.checked_mul(b)), a * b),
+            BinaryOp::Divide | BinaryOp::Remainder if b == 0.0 => {
+                return Err(FormattingError::DivisionByZero(self.source.clone()))
+            }
+            BinaryOp::Divide => Scalar::Float(a / b),
+            BinaryOp::Remainder => int_or_float(ints.and_then(|(a, b)| a.checked_rem(b)), a % b),
+            BinaryOp::Equal => Scalar::Bool(ordering == Some(Ordering::Equal)),
+            BinaryOp::NotEqual => Scalar::Bool(ordering != Some(Ordering::Equal)),
+            BinaryOp::Less => Scalar::Bool(ordering == Some(Ordering::Less)),
+            BinaryOp::LessOrEqual => Scalar::Bool(
+                ordering == Some(Ordering::Less) || ordering == Some(Ordering::Equal),
+            ),
+            BinaryOp::Greater => Scalar::Bool(ordering == Some(Ordering::Greater)),
+            BinaryOp::GreaterOrEqual => Scalar::Bool(
+                ordering == Some(Ordering::Greater) || ordering == Some(Ordering::Equal),
+            ),
+            BinaryOp::And | BinaryOp::Or => unreachable!("boolean operators are short-circuited"),
+        };
+        Ok(res)
+    }
+
+    fn mismatch(&self, value: &str) -> FormattingError {
+        FormattingError::TypeMismatch(self.source.clone(), value.to_string())
+    }
+}
+
+/// Integer operations fall back to floats when they overflow.
+fn int_or_float(int: Option<i64>, float: f64) -> Scalar {
+    match int {
+        Some(i) => Scalar::Int(i),
+        None => Scalar::Float(float),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name expressions;
+        use galvanic_assert::matchers::*;
+
+        use expr::*;
+        use FormattingError;
+
+        fn eval(source: &str) -> Result<Scalar, FormattingError> {
+            let expr = parse_expression(source).expect("Failed to parse");
+            expr.evaluate(&|name: &[String]| match name.join(".").as_str() {
+                "a" => Ok("6".to_string()),
+                "b" => Ok("4".to_string()),
+                "f" => Ok("0.5".to_string()),
+                "yes" => Ok("true".to_string()),
+                "s" => Ok("text".to_string()),
+                "x.y" => Ok("-2".to_string()),
+                n => Err(FormattingError::UnknownFmt(n.to_string(), vec![])),
+            })
+        }
+
+        test precedence() {
+            assert_that!(&eval("1 + 2 * 3"), eq(Ok(Scalar::Int(7))));
+            assert_that!(&eval("(1 + 2) * 3"), eq(Ok(Scalar::Int(9))));
+            assert_that!(&eval("-a + 10 % 4"), eq(Ok(Scalar::Int(-4))));
+            assert_that!(&eval("a - b - 1"), eq(Ok(Scalar::Int(1))));
+        }
+
+        test arithmetic() {
+            assert_that!(&eval("a / b * 100"), eq(Ok(Scalar::Float(150.0))));
+            assert_that!(&eval("a * f"), eq(Ok(Scalar::Float(3.0))));
+            assert_that!(&eval("x.y*x.y"), eq(Ok(Scalar::Int(4))));
+            assert_that!(&eval("9223372036854775807 + 1"),
+                         eq(Ok(Scalar::Float(9223372036854775808.0))));
+        }
+
+        test comparisons() {
+            assert_that!(&eval("a > b"), eq(Ok(Scalar::Bool(true))));
+            assert_that!(&eval("a <= 5"), eq(Ok(Scalar::Bool(false))));
+            assert_that!(&eval("f == 0.5"), eq(Ok(Scalar::Bool(true))));
+            assert_that!(&eval("yes != false"), eq(Ok(Scalar::Bool(true))));
+        }
+
+        test booleans() {
+            assert_that!(&eval("a > b and not yes"), eq(Ok(Scalar::Bool(false))));
+            assert_that!(&eval("a < b or yes"), eq(Ok(Scalar::Bool(true))));
+            // Short-circuited, so the unknown name is never looked up.
+            assert_that!(&eval("yes or missing"), eq(Ok(Scalar::Bool(true))));
+        }
+
+        test type_mismatch() {
+            assert_that!(&eval("s + 1"), eq(Err(FormattingError::TypeMismatch(
+                "s + 1".to_string(), "text".to_string()))));
+            assert_that!(&eval("yes * 2"), eq(Err(FormattingError::TypeMismatch(
+                "yes * 2".to_string(), "true".to_string()))));
+            assert_that!(&eval("a and yes"), eq(Err(FormattingError::TypeMismatch(
+                "a and yes".to_string(), "6".to_string()))));
+        }
+
+        test division_by_zero() {
+            assert_that!(&eval("a / 0"), eq(Err(FormattingError::DivisionByZero(
+                "a / 0".to_string()))));
+            assert_that!(&eval("a % (b - 4)"), eq(Err(FormattingError::DivisionByZero(
+                "a % (b - 4)".to_string()))));
+        }
+
+        test malformed() {
+            for s in &["a +", "(a", "a)", "a = b", "a < b < c", "and a", "a..b", "1x"] {
+                assert_that!(&parse_expression(s), eq(None));
+            }
+        }
+
+    }
+}
@@ -0,0 +1,232 @@
+//! User-defined post-processing filters.
+//!
+//! A filter is a named string transformation, registered once in a
+//! `FilterRegistry` and then available to any placeholder, either via the
+//! `filter` option or the pipe syntax. Filters run on the output of a `Fmt`,
+//! so they work the same regardless of the type of the value being formatted.
+//!
+//! A few filters are built in and available even without a registry. They
+//! can be overridden by registering a filter with the same name.
+//! * `upper` and `lower` change the case of the string;
+//! * `trim` removes leading and trailing whitespace;
+//! * `truncate(spec)` and `width(spec)` work exactly like the common options
+//!   of the same names;
+//! * `escape(name)` works like the `escape` option.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use escape::Escaper;
+use parse::FilterCall;
+use {util, FormattingError};
+
+type FilterFn = dyn Fn(&str, &[String]) -> Result<String, String>;
+
+/// A collection of named filters. Pass it to `FormatTable::format_with` as
+/// a part of a `Context` to make the filters available in a format string.
+#[derive(Default)]
+pub struct FilterRegistry {
+    filters: HashMap<String, Box<FilterFn>>,
+}
+
+impl FilterRegistry {
+    pub fn new() -> Self {
+        FilterRegistry {
+            filters: HashMap::new(),
+        }
+    }
+
+    /// Registers a filter under the given name, replacing any filter that was
+    /// registered under it before. The filter receives the string to
+    /// transform and the arguments it was invoked with. An `Err` returned
+    /// from it is reported as `FormattingError::FilterFailed`.
+    pub fn register<F>(&mut self, name: &str, filter: F)
+    where
+        F: Fn(&str, &[String]) -> Result<String, String> + 'static,
+    {
+        self.filters.insert(name.to_string(), Box::new(filter));
+    }
+
+    /// Removes a filter from the registry, returning whether it was there.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.filters.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    /// Runs the named filter on the input.
+    pub fn apply(&self, name: &str, input: &str, args: &[String]) -> Result<String, FormattingError> {
+        match self.filters.get(name) {
+            Some(filter) => {
+                filter(input, args).map_err(|msg| FormattingError::FilterFailed(name.to_string(), msg))
+            }
+            None => Err(FormattingError::UnknownFilter(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Debug for FilterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names: Vec<&String> = self.filters.keys().collect();
+        names.sort();
+        f.debug_struct("FilterRegistry").field("filters", &names).finish()
+    }
+}
+
+/// Applies the filters listed in the `filter` option (a comma-separated list
+/// of names) to the string, in order.
+pub(crate) fn apply_filter_option(
+    s: String,
+    options: &HashMap<String, String>,
+    registry: Option<&FilterRegistry>,
+) -> Result<String, FormattingError> {
+    let list = match options.get("filter") {
+        Some(list) => list,
+        None => return Ok(s),
+    };
+    let mut res = s;
+    for name in list.split(',').map(|n| n.trim()).filter(|n| !n.is_empty()) {
+        res = run(registry, name, &res, &[])?;
+    }
+    Ok(res)
+}
+
+/// Applies a chain of filters given with the pipe syntax, in order.
+pub(crate) fn apply_filter_chain(
+    s: String,
+    chain: &[FilterCall],
+    registry: Option<&FilterRegistry>,
+) -> Result<String, FormattingError> {
+    let mut res = s;
+    for call in chain.iter() {
+        res = run(registry, &call.name, &res, &call.args)?;
+    }
+    Ok(res)
+}
+
+/// Runs a filter, looking it up first in the registry, then among the
+/// built-in ones.
+fn run(
+    registry: Option<&FilterRegistry>,
+    name: &str,
+    input: &str,
+    args: &[String],
+) -> Result<String, FormattingError> {
+    if let Some(registry) = registry {
+        if registry.contains(name) {
+            return registry.apply(name, input, args);
+        }
+    }
+    match name {
+        "upper" => no_args(name, args).map(|_| input.to_uppercase()),
+        "lower" => no_args(name, args).map(|_| input.to_lowercase()),
+        "trim" => no_args(name, args).map(|_| input.trim().to_string()),
+        "truncate" => {
+            let options = single_arg_option(name, args)?;
+            let mut res = input.to_string();
+            util::apply_truncation(&mut res, &options)?;
+            Ok(res)
+        }
+        "width" => {
+            let options = single_arg_option(name, args)?;
+            let mut res = input.to_string();
+            util::apply_width(&mut res, &options)?;
+            Ok(res)
+        }
+        "escape" => {
+            let options = single_arg_option(name, args)?;
+            let escaper = &options["escape"];
+            match Escaper::from_name(escaper) {
+                Some(escaper) => Ok(escaper.escape(input)),
+                None if escaper == "none" => Ok(input.to_string()),
+                None => Err(FormattingError::InvalidOptionValue(
+                    "escape".to_string(),
+                    escaper.to_string(),
+                )),
+            }
+        }
+        _ => Err(FormattingError::UnknownFilter(name.to_string())),
+    }
+}
+
+fn no_args(name: &str, args: &[String]) -> Result<(), FormattingError> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(FormattingError::FilterFailed(
+            name.to_string(),
+            "expected no arguments".to_string(),
+        ))
+    }
+}
+
+/// Turns the only argument of a built-in filter into an option of the same
+/// name, so that it can be handled by the code for the common options.
+fn single_arg_option(name: &str, args: &[String]) -> Result<HashMap<String, String>, FormattingError> {
+    if args.len() != 1 {
+        return Err(FormattingError::FilterFailed(
+            name.to_string(),
+            "expected exactly one argument".to_string(),
+        ));
+    }
+    let mut options = HashMap::new();
+    options.insert(name.to_string(), args[0].clone());
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name registry;
+        use galvanic_assert::matchers::*;
+
+        use filter::{run, FilterRegistry};
+        use FormattingError;
+
+        test apply() {
+            let mut registry = FilterRegistry::new();
+            registry.register("upper", |s, _| Ok(s.to_uppercase()));
+            let s = registry.apply("upper", "abc", &[]);
+            assert_that!(&s, eq(Ok("ABC".to_string())));
+        }
+
+        test unknown() {
+            let registry = FilterRegistry::new();
+            let s = registry.apply("upper", "abc", &[]);
+            assert_that!(&s, eq(Err(FormattingError::UnknownFilter("upper".to_string()))));
+        }
+
+        test failure() {
+            let mut registry = FilterRegistry::new();
+            registry.register("fail", |_, _| Err("nope".to_string()));
+            let s = registry.apply("fail", "abc", &[]);
+            assert_that!(&s, eq(Err(FormattingError::FilterFailed(
+                "fail".to_string(), "nope".to_string()))));
+        }
+
+        test builtins_can_be_overridden() {
+            let mut registry = FilterRegistry::new();
+            registry.register("upper", |s, _| Ok(format!("<{}>", s)));
+            let s = run(Some(&registry), "upper", "a", &[]);
+            assert_that!(&s, eq(Ok("<a>".to_string())));
+        }
+
+        test builtin_arguments() {
+            let s = run(None, "width", "a", &["r3".to_string()]);
+            assert_that!(&s, eq(Ok("  a".to_string())));
+            let s = run(None, "upper", "a", &["x".to_string()]);
+            assert_that!(&s, eq(Err(FormattingError::FilterFailed(
+                "upper".to_string(), "expected no arguments".to_string()))));
+        }
+
+        test unregister() {
+            let mut registry = FilterRegistry::new();
+            registry.register("id", |s, _| Ok(s.to_string()));
+            assert_that!(&registry.unregister("id"), eq(true));
+            assert_that!(&registry.contains("id"), eq(false));
+        }
+
+    }
+}
@@ -0,0 +1,512 @@
+//! Built-in `Fmt`s that work like functions: instead of formatting a value of
+//! their own, they compute their output from their (already evaluated)
+//! arguments. Since an argument can be a placeholder itself, this allows for
+//! a bit of logic right in a format string.
+//!
+//! None of them is available by default. Put the ones you need in a table, or
+//! get all of them at once from `table`, and combine it with your own table
+//! using a tuple:
+//! ```
+//! use std::collections::HashMap;
+//! use pfmt::{Fmt, FormatTable};
+//! use pfmt::functions;
+//!
+//! let name = "Alice";
+//! let mut table: HashMap<&str, &Fmt> = HashMap::new();
+//! table.insert("name", &name);
+//! let tables = (table, functions::table());
+//! let s = tables.format("{join{Hello:{name}}::sep=, }{repeat{!:3}}").unwrap();
+//! assert_eq!(s, "Hello, Alice!!!");
+//! ```
+//! All of them support the common options, which are applied to the result,
+//! and none of them has sub-`Fmt`s. `if` and `coalesce` only evaluate the
+//! arguments they need, so the ones they skip may even fail to format.
+
+use std::cmp;
+use std::collections::HashMap;
+
+use value::{self, Value};
+use {util, Fmt, FormattingError, SingleFmtError};
+
+/// The options of `join` and `repeat`: the common ones and `sep`.
+const SEP_OPTIONS: &[&str] = &[
+    "sep", "wrap", "indent", "prefix", "column", "truncate", "width", "block", "filter",
+    "escape",
+];
+
+/// The longest output, in characters, that `repeat` and `pad` may produce.
+/// Asking them for more is an `InvalidArgument`, so that a format string
+/// can't make them allocate without a limit.
+pub const MAX_OUTPUT_LEN: usize = 1 << 20;
+
+/// Returns a table with all the functions from this module, under their
+/// usual names: `join`, `repeat`, `pad`, `substr`, `replace`, `if` and
+/// `coalesce`.
+pub fn table() -> HashMap<&'static str, &'static dyn Fmt> {
+    let mut table: HashMap<&'static str, &'static dyn Fmt> = HashMap::new();
+    table.insert("join", &Join);
+    table.insert("repeat", &Repeat);
+    table.insert("pad", &Pad);
+    table.insert("substr", &Substr);
+    table.insert("replace", &Replace);
+    table.insert("if", &If);
+    table.insert("coalesce", &Coalesce);
+    table
+}
+
+/// Joins all its arguments, putting the value of the `sep` option (empty by
+/// default) between them: `{join{a:b:c}::sep=-}` gives `"a-b-c"`.
+pub struct Join;
+
+impl Fmt for Join {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        _flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        let sep = options.get("sep").map(|s| s.as_str()).unwrap_or("");
+        finish(args.join(sep), options)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(SEP_OPTIONS)
+    }
+}
+
+/// Repeats its first argument the number of times given by the second one,
+/// putting the value of the `sep` option (empty by default) between the
+/// repetitions: `{repeat{ab:3}}` gives `"ababab"`. The result can be at most
+/// `MAX_OUTPUT_LEN` characters long.
+pub struct Repeat;
+
+impl Fmt for Repeat {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        _flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        check_arg_count(full_name, args, 2, 2)?;
+        let count = parse_unsigned(full_name, &args[1])?;
+        let sep = options.get("sep").map(|s| s.as_str()).unwrap_or("");
+        let len = args[0].chars().count() + sep.chars().count();
+        if count.saturating_mul(cmp::max(len, 1)) > MAX_OUTPUT_LEN {
+            return Err(invalid_argument(full_name, &args[1]));
+        }
+        let parts: Vec<&str> = (0..count).map(|_| args[0].as_str()).collect();
+        finish(parts.join(sep), options)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(SEP_OPTIONS)
+    }
+}
+
+/// Pads its first argument up to the width (in characters) given by the
+/// second one. The optional third argument is the padding character, a space
+/// by default. The flags say how the argument is aligned, the same way the
+/// `width` option does: on the left by default (or with `l`), on the right
+/// with `r`, or centered with `c`. Values that are already long enough are
+/// left alone: `{pad{7:3:0}:r}` gives `"007"`. The width can be at most
+/// `MAX_OUTPUT_LEN`.
+pub struct Pad;
+
+impl Fmt for Pad {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        check_arg_count(full_name, args, 2, 3)?;
+        let width = parse_unsigned(full_name, &args[1])?;
+        if width > MAX_OUTPUT_LEN {
+            return Err(invalid_argument(full_name, &args[1]));
+        }
+        let fill = match args.get(2) {
+            None => ' ',
+            Some(fill) => {
+                let mut chars = fill.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(invalid_argument(full_name, fill)),
+                }
+            }
+        };
+        let len = args[0].chars().count();
+        let missing = width.saturating_sub(len);
+        let (left, right) = if flags.contains(&'r') {
+            (missing, 0)
+        } else if flags.contains(&'c') {
+            (missing / 2, missing - missing / 2)
+        } else {
+            (0, missing)
+        };
+        let mut s = String::with_capacity(args[0].len() + missing);
+        s.extend((0..left).map(|_| fill));
+        s.push_str(&args[0]);
+        s.extend((0..right).map(|_| fill));
+        finish(s, options)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&['l', 'c', 'r'])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
+}
+
+/// Takes a part of its first argument. The second argument is the index of
+/// the first character to take, the optional third one is the number of
+/// characters to take (all the rest by default). A negative index counts from
+/// the end. Parts that are out of range are silently dropped:
+/// `{substr{abcdef:-3:2}}` gives `"de"`.
+pub struct Substr;
+
+impl Fmt for Substr {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        _flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        check_arg_count(full_name, args, 2, 3)?;
+        let len = args[0].chars().count() as i64;
+        let start = args[1]
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| invalid_argument(full_name, &args[1]))?;
+        let start = if start < 0 { (len + start).max(0) } else { start.min(len) };
+        let count = match args.get(2) {
+            Some(count) => parse_unsigned(full_name, count)?,
+            None => len as usize,
+        };
+        let s = args[0].chars().skip(start as usize).take(count).collect();
+        finish(s, options)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
+}
+
+/// Replaces all occurrences of its second argument in its first argument
+/// with its third argument: `{replace{a-b-c:-:+}}` gives `"a+b+c"`.
+pub struct Replace;
+
+impl Fmt for Replace {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        _flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        check_arg_count(full_name, args, 3, 3)?;
+        if args[1].is_empty() {
+            return Err(invalid_argument(full_name, &args[1]));
+        }
+        finish(args[0].replace(&args[1], &args[2]), options)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
+}
+
+/// Chooses between its second and third arguments depending on the first
+/// one. The condition is false if it's empty, `0` or `false` (ignoring
+/// surrounding whitespace), and true otherwise. The third argument is
+/// optional and defaults to an empty string: `{if{{admin}:(admin)}}`.
+pub struct If;
+
+impl Fmt for If {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        _flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        check_arg_count(full_name, args, 2, 3)?;
+        let s = if is_truthy(&args[0]) {
+            args[1].clone()
+        } else {
+            args.get(2).cloned().unwrap_or_default()
+        };
+        finish(s, options)
+    }
+
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        _flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        check_no_subfmt(full_name, name)?;
+        check_arg_count(full_name, args, 2, 3)?;
+        let s = if is_truthy(&args[0].render()?) {
+            args[1].render()?
+        } else {
+            match args.get(2) {
+                Some(arg) => arg.render()?,
+                None => String::new(),
+            }
+        };
+        let (_, options) = value::render_all(&[], options)?;
+        Ok(finish(s, &options)?)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
+}
+
+/// Produces the first of its arguments that is not empty, or an empty string
+/// if all of them are: `{coalesce{{nickname}:{name}:anonymous}}`.
+pub struct Coalesce;
+
+impl Fmt for Coalesce {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        _flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        check_no_subfmt(full_name, name)?;
+        let s = args.iter().find(|arg| !arg.is_empty()).cloned().unwrap_or_default();
+        finish(s, options)
+    }
+
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        _flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        check_no_subfmt(full_name, name)?;
+        let mut s = String::new();
+        for arg in args.iter() {
+            s = arg.render()?;
+            if !s.is_empty() {
+                break;
+            }
+        }
+        let (_, options) = value::render_all(&[], options)?;
+        Ok(finish(s, &options)?)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
+}
+
+/* ---------- helpers ---------- */
+
+fn check_no_subfmt(full_name: &[String], name: &[String]) -> Result<(), SingleFmtError> {
+    if name.is_empty() {
+        Ok(())
+    } else {
+        Err(SingleFmtError::UnknownSubfmt(util::join_name(full_name)))
+    }
+}
+
+fn check_arg_count<T>(
+    full_name: &[String],
+    args: &[T],
+    min: usize,
+    max: usize,
+) -> Result<(), SingleFmtError> {
+    if args.len() < min || args.len() > max {
+        Err(SingleFmtError::InvalidArgumentCount(
+            util::join_name(full_name),
+            args.len(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn parse_unsigned(full_name: &[String], arg: &str) -> Result<usize, SingleFmtError> {
+    arg.trim()
+        .parse::<usize>()
+        .map_err(|_| invalid_argument(full_name, arg))
+}
+
+fn invalid_argument(full_name: &[String], arg: &str) -> SingleFmtError {
+    SingleFmtError::InvalidArgument(util::join_name(full_name), arg.to_string())
+}
+
+fn is_truthy(s: &str) -> bool {
+    !matches!(s.trim(), "" | "0" | "false")
+}
+
+fn finish(mut s: String, options: &HashMap<String, String>) -> Result<String, SingleFmtError> {
+    util::apply_common_options(&mut s, options)?;
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name functions;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use functions;
+        use {Context, Fmt, FormatTable, FormattingError};
+
+        fn table() -> HashMap<&'static str, &'static dyn Fmt> {
+            let mut table = functions::table();
+            table.insert("empty", &"");
+            table.insert("name", &"Bob");
+            table.insert("yes", &true);
+            table.insert("no", &false);
+            table
+        }
+
+        test join() {
+            let s = table().format("{join{a:{name}:c}::sep=-}, {join{a:b}::sep=, }");
+            assert_that!(&s, eq(Ok("a-Bob-c, a, b".to_string())));
+        }
+
+        test repeat() {
+            let s = table().format("{repeat{ab:3}}, {repeat{x:0}}, {repeat{x:3}::sep=-}");
+            assert_that!(&s, eq(Ok("ababab, , x-x-x".to_string())));
+            let s = table().format("{repeat{ab:many}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgument(
+                "repeat".to_string(), "many".to_string()))));
+        }
+
+        test output_limit() {
+            let s = table().format("{repeat{x:99999999999}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgument(
+                "repeat".to_string(), "99999999999".to_string()))));
+            let s = table().format("{repeat{:99999999999}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgument(
+                "repeat".to_string(), "99999999999".to_string()))));
+            let s = table().format("{pad{x:99999999999}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgument(
+                "pad".to_string(), "99999999999".to_string()))));
+            let limit = functions::MAX_OUTPUT_LEN;
+            let s = table().format(&format!("{{repeat{{ab:{}}}}}", limit / 2)).unwrap();
+            assert_that!(&s.len(), eq(limit));
+        }
+
+        test pad() {
+            let s = table().format("[{pad{ab:4}}] [{pad{7:3:0}:r}] [{pad{ab:6:*}:c}] [{pad{abc:2}}]");
+            assert_that!(&s, eq(Ok("[ab  ] [007] [**ab**] [abc]".to_string())));
+            let s = table().format("[{pad{ab:4:.}:l}] [{pad{ab:4:.}:r:width=l6}]");
+            assert_that!(&s, eq(Ok("[ab..] [..ab  ]".to_string())));
+            let s = table().format("{pad{ab:4:xy}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgument(
+                "pad".to_string(), "xy".to_string()))));
+        }
+
+        test substr() {
+            let s = table().format("{substr{abcdef:2}}, {substr{abcdef:1:2}}, {substr{abcdef:-3:2}}");
+            assert_that!(&s, eq(Ok("cdef, bc, de".to_string())));
+            let s = table().format("[{substr{abc:5}}] [{substr{abc:-5:2}}]");
+            assert_that!(&s, eq(Ok("[] [ab]".to_string())));
+        }
+
+        test replace() {
+            let s = table().format("{replace{a-b-c:-:+}}, {replace{{name}:o:0}}");
+            assert_that!(&s, eq(Ok("a+b+c, B0b".to_string())));
+        }
+
+        test if_() {
+            let s = table().format("{if{{yes}:Y:N}} {if{{no}:Y:N}} {if{{empty}:Y:N}} [{if{0:Y}}]");
+            assert_that!(&s, eq(Ok("Y N N []".to_string())));
+        }
+
+        test coalesce() {
+            let s = table().format("{coalesce{{empty}:{name}:x}}, {coalesce{{empty}:x}}");
+            assert_that!(&s, eq(Ok("Bob, x".to_string())));
+        }
+
+        test lazy() {
+            let s = table().format("{if{{yes}:Y:{missing}}} {coalesce{{name}:{missing}}}");
+            assert_that!(&s, eq(Ok("Y Bob".to_string())));
+            let s = table().format("{if{{no}:Y:{missing}}}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("missing".to_string(), vec![]))));
+        }
+
+        test argument_count() {
+            let s = table().format("{replace{a:b}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgumentCount(
+                "replace".to_string(), 2))));
+            let s = table().format("{if{a:b:c:d}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidArgumentCount(
+                "if".to_string(), 4))));
+        }
+
+        test common_options() {
+            let s = table().format("{join{a:b}::sep=-:width=r5}");
+            assert_that!(&s, eq(Ok("  a-b".to_string())));
+        }
+
+        test strict() {
+            let context = Context { strict: true, ..Context::default() };
+            let s = table().format_with("{join{a:b}::sep=-} {pad{a:3:.}:r}", &context);
+            assert_that!(&s, eq(Ok("a-b ..a".to_string())));
+            let s = table().format_with("{if{1:a}::sep=-}", &context);
+            assert_that!(&s, eq(Err(FormattingError::UnknownOption("sep".to_string(), vec![]))));
+        }
+
+        test no_subfmts() {
+            let s = table().format("{join.x{a}}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("join.x".to_string(), vec![]))));
+        }
+
+    }
+}
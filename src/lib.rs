@@ -30,9 +30,15 @@
  * Some `Fmt`s also support options, which are specified after the flags (and
  * if you want to use options, you need a flags section, even if it's empty)
  * and are separated by colons: `"{foobar::option1=value1:option2=value2}"`.
- * There aren't too many options at the moment. There is also a possibility of
- * giving arguments to a placeholder, but there's no implementation (yet) of a
- * `FormatTable` that takes advantage of it.
+ * There aren't too many options at the moment. A placeholder can also be
+ * given arguments, which are written right after the name, in brackets and
+ * separated by colons: `"{foobar{arg1:arg2}}"`. An argument can be a
+ * placeholder itself, which is formatted before being passed on. Arguments
+ * are mostly useful with the function-like `Fmt`s from the `functions`
+ * module, like `join` or `if`. A `Fmt` that wants to evaluate its arguments
+ * and options only when needed, or to get at them as numbers or `Fmt`s
+ * rather than strings, can implement `Fmt::format_values` (see the `value`
+ * module).
  *
  * See each implementation's entry to learn all the options and flags it
  * supports.
@@ -148,7 +154,8 @@
  * let s = table.format("{p.x}, {p.y}").unwrap();
  * assert_eq!(s, "1, 2");
  * ```
- * This can be nested to arbitrary depth.
+ * This can be nested to arbitrary depth. Whole format tables can be nested the
+ * same way, see `tables::Namespace` and `tables::Mounted`.
  *
  * # Errors
  * `format` method on `FormatTables` returns a `Result<String,
@@ -160,12 +167,84 @@
  * With hard-coded format strings and rigid format tables, most of these can be
  * safely ignored, so `unwrap()` away.
  *
+ * By default, flags and options that a `Fmt` doesn't know are ignored, so a
+ * typo in one does nothing at all. Setting `strict` in a `Context` turns
+ * them into `UnknownFlag` and `UnknownOption` errors for the `Fmt`s that
+ * declare what they know (see `Fmt::known_flags`), which all the ones from
+ * this crate do. The ones reached with a dotted name are checked as well,
+ * as long as the `Fmt`s on the way can look them up (see
+ * `Fmt::sub_known_flags`), like `tables::Namespace` does.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Context, Fmt, FormatTable, FormattingError};
+ *
+ * let n = 42;
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("n", &n);
+ * let context = Context { strict: true, ..Context::default() };
+ * let s = table.format_with("{n::widht=r5}", &context);
+ * let suggestions = vec!["width".to_string()];
+ * assert_eq!(s, Err(FormattingError::UnknownOption("widht".to_string(), suggestions)));
+ * assert_eq!(table.format("{n::widht=r5}").unwrap(), "42");
+ * ```
+ * As shown above, `UnknownOption` comes with the options that are close to
+ * the unknown one, which is what was most likely meant. `UnknownFmt` does
+ * the same for names, as long as the table can list the names it has (see
+ * `FormatTable::names`), and for the dotted names, as long as the `Fmt`s
+ * can list their sub-`Fmt`s (see `Fmt::sub_names`). The tables from the
+ * standard collections and this crate can, except those producing their
+ * `Fmt`s on the fly.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable, FormattingError};
+ * use pfmt::tables::Mounted;
+ *
+ * let mut server: HashMap<&str, Box<Fmt>> = HashMap::new();
+ * server.insert("hostname", Box::new("example.org"));
+ * let table = Mounted::new("server", server);
+ * let s = table.format("{server.hostnmae}");
+ * let suggestions = vec!["server.hostname".to_string()];
+ * assert_eq!(s, Err(FormattingError::UnknownFmt("server.hostnmae".to_string(), suggestions)));
+ * ```
+ *
  * # Common options
  * Most pre-made implementation of `Fmt` honor several common options. Here's
  * a list of them, with detailed info available further in this section:
+ * * `wrap`, along with `indent`, `prefix` and `column`
  * * `truncate`
  * * `width`
  *
+ * They are applied in the order given above.
+ *
+ * ## `wrap`: `non-negative integer`
+ * Word-wraps the field so that no line of the output goes past the given
+ * column, unless a single word is too long to fit. Runs of whitespace
+ * between words are collapsed into single spaces, line breaks already present
+ * in the field are kept.
+ *
+ * The wrapping takes into account the column the field starts at in the
+ * output, so the first line is shorter if the placeholder is preceded by
+ * some text. This is detected automatically for the placeholders at the top
+ * level of a format string, and can be overridden with the `column` option
+ * (`non-negative integer`).
+ *
+ * Continuation lines are started with `indent` (`non-negative integer`)
+ * spaces followed by `prefix` (any string), both of which are empty by
+ * default.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable};
+ *
+ * let text = "the quick brown fox jumps over the lazy dog";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("text", &text);
+ * let s = table.format("help\\: {text::wrap=20:indent=6}").unwrap();
+ * assert_eq!(s, "help: the quick\n      brown fox\n      jumps over the\n      lazy dog");
+ * ```
+ *
+ * It is an `InvalidOptionValue` to pass anything but a non-negative integer
+ * to `wrap`, `indent` or `column`.
+ *
  * ## `truncate`: `{'l', 'r'} + non-negative integer`
  * Controls truncation of the field. If begins with `l`, left part of the
  * field that doesn't fit is truncated, if begins with `r` - the right part is
@@ -175,6 +254,9 @@
  * It is an `InvalidOptionValue` to pass anything not fitting into the template
  * in the header as the value of this option.
  *
+ * If the field consists of several lines, each of them is truncated
+ * separately.
+ *
  * ## `width`: `{'l', 'c', 'r'} + non-negative integer`
  * Controls the width of the field. Has no effect if the field is already wider
  * than the value supplied. If starts with "`l`", the field will be
@@ -184,6 +266,23 @@
  * It is an `InvalidOptionValue` to pass anything not fitting into the template
  * in the header as the value for this option.
  *
+ * If the field consists of several lines, each of them is padded and
+ * justified separately. If the `block` option (which takes no value) is
+ * present, the lines are instead justified together, as a block as wide as
+ * the widest of them, so they keep their alignment relative to each other.
+ * Either way every line ends up at least as wide as requested, which allows
+ * multi-line fields to be placed in columns.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable};
+ *
+ * let s = "a\nbbb";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("s", &s);
+ * assert_eq!(table.format("{s::width=r5}").unwrap(), "    a\n  bbb");
+ * assert_eq!(table.format("{s::width=r5:block}").unwrap(), "  a  \n  bbb");
+ * ```
+ *
  * # Common numeric options
  * Most numeric Fmts honor these. For the detailed description skip to the end
  * of this section.
@@ -206,6 +305,194 @@
  * It is an `InvalidOptionValue` to pass a string different from the mentioned
  * three to this option.
  *
+ * # Escaping
+ * Any placeholder, whatever its `Fmt`, accepts the `escape` option, which
+ * escapes the produced string for inclusion into some other language. The
+ * escaping is done after the `Fmt` (and its common options) did its job.
+ * Recognised values are:
+ * * `html` - replaces `&`, `<`, `>`, `"` and `'` with entities;
+ * * `json` - escapes the value for use inside a double-quoted JSON string;
+ * * `shell` - wraps the value in single quotes for a POSIX shell;
+ * * `url` - percent-encodes everything but the unreserved characters;
+ * * `csv` - quotes the value as a CSV field, if it needs quoting;
+ * * `none` - doesn't escape anything.
+ *
+ * It is an `InvalidOptionValue` to pass anything else to this option.
+ *
+ * An escaper for all the placeholders in a format string can be set with the
+ * `format_with` method and a `Context`. Placeholders with their own `escape`
+ * option ignore it, which is what `escape=none` is for.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Context, Fmt, FormatTable};
+ * use pfmt::escape::Escaper;
+ *
+ * let user = "<script>";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("user", &user);
+ * let context = Context { escaper: Some(Escaper::Html), ..Context::default() };
+ * let s = table.format_with("<b>{user}</b> {user::escape=url}", &context).unwrap();
+ * assert_eq!(s, "<b>&lt;script&gt;</b> %3Cscript%3E");
+ * ```
+ *
+ * # Filters
+ * The output of any placeholder can be passed through a chain of filters,
+ * written after the options and separated by pipes:
+ * `"{foobar::width=l10 | upper | truncate(r5)}"`. The chain starts at the
+ * first pipe with whitespace on both sides; any other pipe in the name, the
+ * flags or the option values is taken literally, so `"{foobar::sep=|}"`
+ * still has `|` as the value of `sep`. Filters are applied left to right, so
+ * unlike with the common options the order of transformations is up to the
+ * format string. Some filters take arguments, which are given in parentheses
+ * and separated by commas. In a filter chain, a pipe, a comma or a
+ * parenthesis can be escaped with a backslash, and whitespace around the
+ * pipes and the arguments is ignored.
+ *
+ * Several filters are built in: `upper`, `lower`, `trim`, and `truncate`,
+ * `width` and `escape`, each of which takes a single argument and works like
+ * the option of the same name.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable};
+ *
+ * let s = "abc";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("s", &s);
+ * let s = table.format("[{s | upper | width(r5) | truncate(r4)}]").unwrap();
+ * assert_eq!(s, "[  AB]");
+ * ```
+ * Your own string transformations can be registered in a `FilterRegistry`,
+ * which is passed to `format_with` in a `Context`. Registered filters are
+ * available in the pipe syntax and in the `filter` option, which takes a
+ * comma-separated list of filter names. Filters from the option are run
+ * first, then the ones from the pipe chain, and the escaping comes last.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Context, Fmt, FormatTable};
+ * use pfmt::filter::FilterRegistry;
+ *
+ * let mut filters = FilterRegistry::new();
+ * filters.register("mask", |s, _| {
+ *     let len = s.chars().count();
+ *     Ok(s.chars().enumerate().map(|(i, c)| if i + 4 < len { '*' } else { c }).collect())
+ * });
+ * filters.register("brackets", |s, _| Ok(format!("[{}]", s)));
+ * let card = "1234567812345678";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("card", &card);
+ * let context = Context { filters: Some(&filters), ..Context::default() };
+ * let s = table.format_with("{card::filter=mask,brackets}", &context).unwrap();
+ * assert_eq!(s, "[************5678]");
+ * ```
+ * Referring to a filter that is not in the registry is an `UnknownFilter`
+ * error, and a filter returning an error results in a `FilterFailed`.
+ *
+ * # Includes
+ * Fragments shared by several format strings can be registered as named
+ * templates in a `templates::TemplateRegistry`, which is passed to
+ * `format_with` in a `Context`. `{>name}` inserts the template registered
+ * under `name`, formatted with the same table and context. Includes can be
+ * nested, but only up to `templates::MAX_INCLUDE_DEPTH` levels, so a
+ * template including itself results in an `IncludeDepthExceeded` error
+ * rather than an endless loop. Including a template that is not registered
+ * is an `UnknownTemplate` error.
+ *
+ * # Comments and raw blocks
+ * `{# ... #}` is a comment, which produces no output. `{! ... !}` is a raw
+ * block: everything between the markers is output as is, without any
+ * escaping, so it's handy for chunks of JSON or LaTeX. Neither can be
+ * nested, and both end at the first `#}` and `!}` respectively.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable};
+ *
+ * let name = "x";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("name", &name);
+ * let s = table.format("{# the key is fixed #}{!{\"key\": !}\"{name}\"{!}!}").unwrap();
+ * assert_eq!(s, "{\"key\": \"x\"}");
+ * ```
+ *
+ * # Whitespace control
+ * A `~` right after the opening bracket of a placeholder, `{~name}`, strips
+ * all the whitespace (newlines included) in front of it, and a `~` after
+ * whitespace right before the closing bracket, `{name ~}`, strips the
+ * whitespace after it. Without the whitespace in front, `~` is an ordinary
+ * character, so `{path::prefix=~}` still has `~` as the value of `prefix`;
+ * a name that starts with `~` has to have it escaped. This works for
+ * includes, comments and raw blocks as well (after the end of a comment or
+ * a raw block, `#~}` and `!~}` need no whitespace), and is done once, when
+ * parsing, so it costs nothing when formatting. It keeps multi-line
+ * templates readable without stray blank lines in the output.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable};
+ *
+ * let host = "db1";
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("host", &host);
+ * let s = table.format("[server]\n{# the rest is generated #~}\nhost = {host}").unwrap();
+ * assert_eq!(s, "[server]\nhost = db1");
+ * ```
+ *
+ * # Expressions
+ * Instead of a name, a placeholder can hold an expression over the values of
+ * numeric (and boolean) `Fmt`s, marked with a `=` in front:
+ * `{=used / total}`. Without the `=`, operator characters and whitespace are
+ * just a part of the name, so `{user-name}` still refers to `user-name`. The
+ * operands are numbers, `true`, `false`, and names, which
+ * are formatted with no flags or options and read back as numbers or
+ * booleans. The operators are, from the loosest binding to the tightest:
+ * `or`; `and`; `not`; comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`); `+`
+ * and `-`; `*`, `/` and `%`; unary `-`. Parentheses work as usual.
+ *
+ * Arithmetic on integers gives integers, except for `/`, which always gives
+ * a float. The result is formatted as an `i64`, an `f64` or a `bool`, so
+ * it takes the flags, options and filters of those. An expression cannot
+ * take arguments.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Fmt, FormatTable};
+ *
+ * let used = 3;
+ * let total = 8;
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("used", &used);
+ * table.insert("total", &total);
+ * let s = table.format("{=used / total * 100 ::prec=1}%, {=used > 0 and used < total:y}").unwrap();
+ * assert_eq!(s, "37.5%, yes");
+ * ```
+ * A malformed expression is an `InvalidExpression` error, an operand of the
+ * wrong type (like a string in arithmetic, or a number next to `and`) is a
+ * `TypeMismatch`, and dividing by zero is a `DivisionByZero`.
+ *
+ * # Custom syntax
+ * If the output is full of brackets or colons, escaping all of them gets
+ * old fast. The placeholder delimiters (which can be longer than one
+ * character, like `{{` and `}}`), the separator, the dot in names, the `=`
+ * of options and the escape character can all be changed with a
+ * `syntax::Syntax`, passed to `format_with` in a `Context`. Setting up a
+ * syntax in which these can't be told apart is a `syntax::SyntaxError`. An
+ * unescaped closing delimiter outside of a placeholder is an
+ * `UnmatchedClosingDelimiter` error. `Syntax::literal_separators` makes
+ * colons outside of placeholders ordinary text, so that URLs and times in
+ * literals don't need escaping.
+ * ```
+ * use std::collections::HashMap;
+ * use pfmt::{Context, Fmt, FormatTable};
+ * use pfmt::syntax::Syntax;
+ *
+ * let h = 12;
+ * let mut table: HashMap<&str, &Fmt> = HashMap::new();
+ * table.insert("h", &h);
+ * let context = Context {
+ *     syntax: Syntax::default().literal_separators(true),
+ *     ..Context::default()
+ * };
+ * let s = table.format_with("lunch: {h}:30", &context).unwrap();
+ * assert_eq!(s, "lunch: 12:30");
+ * ```
+ *
  * # More fun
  * Format tables are not required to actually *hold* the `Fmt`s. They can
  * produce those on the fly, if you make them to. You only need to implement
@@ -231,13 +518,19 @@
  * let s = table.format("{1}, {12}").unwrap();
  * assert_eq!(s, "1, 12");
  * ```
- * The above example is not particularly useful, but shows the point.
+ * The above example is not particularly useful, but shows the point. The same
+ * table can be written inline with `adapters::FnTable`, and there's also
+ * `adapters::FnFmt` to make a `Fmt` out of a closure. The `tables` module
+ * contains some ready-made tables, like `EnvTable`, which looks up
+ * environment variables, and wrappers like `Cached`, which makes sure that
+ * a table that produces its `Fmt`s on the fly produces each of them only once.
  *
  * There's also an implementation of `FormatTable` for tuples (up to 6-tuples)
  * that contain format tables. When encountering a placeholder, it first
  * searches for the relevant `Fmt` in the first table, then in the second and
  * so on. This allows to easily override some `Fmt`s or provide defaults
- * without changing the tables themselves.
+ * without changing the tables themselves. If the number of layers is not
+ * known in advance, or changes during the rendering, use `tables::Scopes`.
  * ```
  * use std::collections::HashMap;
  * use pfmt::{Fmt, FormatTable};
@@ -268,11 +561,28 @@ use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::ops::Deref;
 
-use parse::{parse, ParseError, Piece};
+use escape::Escaper;
+use filter::FilterRegistry;
+use expr::Scalar;
+use parse::{parse, FilterCall, ParseError, Piece};
+use syntax::Syntax;
+use templates::{TemplateRegistry, MAX_INCLUDE_DEPTH};
+use value::{Evaluator, Value};
 
+mod expr;
 mod parse;
 
+pub mod adapters;
+pub mod escape;
+pub mod filter;
+pub mod functions;
+pub mod lint;
+pub mod pretty;
+pub mod syntax;
+pub mod tables;
+pub mod templates;
 pub mod util;
+pub mod value;
 
 /* ---------- base traits ---------- */
 
@@ -287,42 +597,319 @@ pub trait Fmt {
         flags: &[char],
         options: &HashMap<String, String>,
     ) -> Result<String, SingleFmtError>;
+
+    /// Like `format`, but receives the arguments and options as lazily
+    /// evaluated `Value`s. This is what the formatting machinery actually
+    /// calls; the default implementation renders all the values and passes
+    /// them on to `format`. Override it to skip evaluating the values that
+    /// are not needed, or to work with them as numbers or `Fmt`s.
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        let (args, options) = value::render_all(args, options)?;
+        Ok(self.format(full_name, name, &args, flags, &options)?)
+    }
+
+    /// The flags this `Fmt` is aware of, or `None` (the default) if it
+    /// doesn't say. In strict mode (see `Context::strict`), a placeholder
+    /// naming this `Fmt` fails with an `UnknownFlag` if it has any other
+    /// flag. Placeholders naming one of its sub-`Fmt`s are checked against
+    /// `sub_known_flags` instead.
+    fn known_flags(&self) -> Option<&'static [char]> {
+        None
+    }
+
+    /// The options this `Fmt` recognises, or `None` (the default) if it
+    /// doesn't say. Like `known_flags`, this is what strict mode checks the
+    /// options against, failing with an `UnknownOption`.
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        None
+    }
+
+    /// The names of the sub-`Fmt`s under the given path (relative to this
+    /// `Fmt`, so an empty one gives the direct sub-`Fmt`s), or `None` (the
+    /// default) if it can't tell. Only used to suggest names when a sub-`Fmt`
+    /// is not found.
+    fn sub_names(&self, _name: &[String]) -> Option<Vec<String>> {
+        None
+    }
+
+    /// The flags the `Fmt` under the given path (relative to this `Fmt`, so
+    /// an empty one means this `Fmt` itself) is aware of. This is what strict
+    /// mode actually checks; the default only knows about this `Fmt` itself,
+    /// so `Fmt`s with sub-`Fmt`s override it to look them up.
+    fn sub_known_flags(&self, name: &[String]) -> Option<&'static [char]> {
+        if name.is_empty() {
+            self.known_flags()
+        } else {
+            None
+        }
+    }
+
+    /// The options the `Fmt` under the given path recognises, like
+    /// `sub_known_flags`.
+    fn sub_known_options(&self, name: &[String]) -> Option<&'static [&'static str]> {
+        if name.is_empty() {
+            self.known_options()
+        } else {
+            None
+        }
+    }
 }
 
 pub trait FormatTable {
     fn get_fmt<'a, 'b>(&'a self, name: &'b str) -> Option<BoxOrRef<'a, dyn Fmt>>;
 
+    /// The names this table has `Fmt`s for, or `None` (the default) if it
+    /// can't tell, like a table producing its `Fmt`s on the fly. Only used
+    /// to suggest names when one is not found.
+    fn names(&self) -> Option<Vec<String>> {
+        None
+    }
+
     fn format(&self, input: &str) -> Result<String, FormattingError> {
-        let pieces = parse(input)?;
-        let mut res = String::new();
-        for piece in pieces.iter() {
-            res.push_str(&format_one(self, piece)?);
+        self.format_with(input, &Context::default())
+    }
+
+    fn format_with(&self, input: &str, context: &Context) -> Result<String, FormattingError> {
+        let pieces = parse(input, &context.syntax)?;
+        format_pieces(self, &pieces, context, Some(0), 0)
+    }
+}
+
+/// Settings that apply to a whole formatting call rather than to a single
+/// placeholder. `Context::default()` gives the behaviour of plain `format`.
+#[derive(Debug, Clone, Default)]
+pub struct Context<'a> {
+    /// Escaper to apply to the output of every top-level placeholder that
+    /// doesn't have an `escape` option of its own.
+    pub escaper: Option<Escaper>,
+    /// Filters available to the `filter` option.
+    pub filters: Option<&'a FilterRegistry>,
+    /// Templates available to the include syntax, `{>name}`.
+    pub templates: Option<&'a TemplateRegistry>,
+    /// The delimiters and special characters of the format string.
+    pub syntax: Syntax,
+    /// Makes the flags and options that a `Fmt` declares it doesn't know
+    /// (see `Fmt::known_flags`) an error, instead of silently ignoring them.
+    pub strict: bool,
+}
+
+/// Formats a sequence of pieces. The column is only known for the top-level
+/// pieces (those of the format string itself, or of templates included
+/// there); these are also the ones the context's escaper applies to.
+fn format_pieces<T: FormatTable + ?Sized>(
+    table: &T,
+    pieces: &[Piece],
+    context: &Context,
+    column: Option<usize>,
+    depth: u8,
+) -> Result<String, FormattingError> {
+    let mut res = String::new();
+    let mut column = column;
+    for piece in pieces.iter() {
+        let mut s = format_one(table, piece, context, column, depth)?;
+        let opts = match piece {
+            Piece::Literal(_) | Piece::Include(_) | Piece::Comment(_) => None,
+            Piece::Placeholder(_, _, _, opts, _) => Some(opts),
+            Piece::Expression(_, _, opts, _) => Some(opts),
+        };
+        if let (Some(opts), Some(escaper), true) = (opts, context.escaper, column.is_some()) {
+            if !opts.contains_key("escape") {
+                s = escaper.escape(&s);
+            }
         }
-        Ok(res)
+        column = column.map(|column| util::column_after(column, &s));
+        res.push_str(&s);
     }
+    Ok(res)
 }
 
 fn format_one<'a, 'b, T: FormatTable + ?Sized>(
     table: &'a T,
     piece: &'b Piece,
+    context: &Context,
+    column: Option<usize>,
+    depth: u8,
 ) -> Result<String, FormattingError> {
     match piece {
         Piece::Literal(s) => Ok(s.clone()),
-        Piece::Placeholder(name, args, flags, opts) => {
+        Piece::Placeholder(name, args, flags, opts, filters) => {
             if let Some(root) = table.get_fmt(&name[0]) {
-                let mut processed_args = Vec::with_capacity(args.len());
-                for arg in args.iter() {
-                    processed_args.push(format_one(table, arg)?);
-                }
-                let mut processed_opts = HashMap::new();
-                for (key, piece) in opts.iter() {
-                    processed_opts.insert(key.clone(), format_one(table, piece)?);
-                }
-                Ok(root.format(name, &name[1..], &processed_args, flags, &processed_opts)?)
+                let unit = Unit { name, args, flags, opts, filters };
+                format_unit(table, &*root, &unit, context, column, depth)
             } else {
-                Err(FormattingError::UnknownFmt(util::join_name(&name)))
+                let suggestions = table
+                    .names()
+                    .map_or_else(Vec::new, |names| util::close_matches(&name[0], &names));
+                Err(FormattingError::UnknownFmt(util::join_name(&name), suggestions))
+            }
+        }
+        Piece::Expression(expression, flags, opts, filters) => {
+            let lookup = |name: &[String]| {
+                let piece = Piece::Placeholder(
+                    name.to_vec(),
+                    Vec::new(),
+                    Vec::new(),
+                    HashMap::new(),
+                    Vec::new(),
+                );
+                format_one(table, &piece, context, None, depth)
+            };
+            let root: Box<dyn Fmt> = match expression.evaluate(&lookup)? {
+                Scalar::Int(i) => Box::new(i),
+                Scalar::Float(f) => Box::new(f),
+                Scalar::Bool(b) => Box::new(b),
+            };
+            let name = [expression.source.clone()];
+            let unit = Unit { name: &name, args: &[], flags, opts, filters };
+            format_unit(table, &*root, &unit, context, column, depth)
+        }
+        Piece::Include(name) => {
+            let pieces = context
+                .templates
+                .and_then(|templates| templates.get(name))
+                .ok_or_else(|| FormattingError::UnknownTemplate(name.clone()))?;
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(FormattingError::IncludeDepthExceeded(name.clone()));
             }
+            format_pieces(table, pieces, context, column, depth + 1)
         }
+        Piece::Comment(_) => Ok(String::new()),
+    }
+}
+
+/// The parts of a placeholder that are passed on to its `Fmt`.
+struct Unit<'p> {
+    name: &'p [String],
+    args: &'p [Piece],
+    flags: &'p [char],
+    opts: &'p HashMap<String, Piece>,
+    filters: &'p [FilterCall],
+}
+
+/// Formats a placeholder once its `Fmt` is known.
+fn format_unit<T: FormatTable + ?Sized>(
+    table: &T,
+    root: &dyn Fmt,
+    unit: &Unit,
+    context: &Context,
+    column: Option<usize>,
+    depth: u8,
+) -> Result<String, FormattingError> {
+    let evaluator = TableEvaluator { table, context, depth };
+    let values: Vec<Value> = unit.args.iter().map(|arg| Value::lazy(arg, &evaluator)).collect();
+    let mut options: HashMap<String, Value> = unit
+        .opts
+        .iter()
+        .map(|(key, piece)| (key.clone(), Value::lazy(piece, &evaluator)))
+        .collect();
+    if let (Some(column), true) = (column, options.contains_key("wrap")) {
+        options
+            .entry("column".to_string())
+            .or_insert_with(|| Value::from(column.to_string()));
+    }
+    let name = unit.name;
+    if context.strict {
+        check_known(root, unit)?;
+    }
+    let res = root
+        .format_values(name, &name[1..], &values, unit.flags, &options)
+        .map_err(|e| suggest(root, name, e))?;
+    // Only the options used below are rendered here, the rest is up to the
+    // Fmt.
+    let mut processed_opts = HashMap::new();
+    for key in ["filter", "escape"].iter() {
+        if let Some(value) = options.get(*key) {
+            processed_opts.insert(key.to_string(), value.render()?);
+        }
+    }
+    let res = filter::apply_filter_option(res, &processed_opts, context.filters)?;
+    let res = filter::apply_filter_chain(res, unit.filters, context.filters)?;
+    Ok(util::apply_escaping(res, &processed_opts)?)
+}
+
+/// Fails on the first flag or option of the placeholder that its `Fmt`
+/// declares it doesn't know.
+fn check_known(root: &dyn Fmt, unit: &Unit) -> Result<(), FormattingError> {
+    let path = &unit.name[1..];
+    if let Some(known) = root.sub_known_flags(path) {
+        if let Some(flag) = unit.flags.iter().find(|flag| !known.contains(flag)) {
+            return Err(FormattingError::UnknownFlag(*flag));
+        }
+    }
+    if let Some(known) = root.sub_known_options(path) {
+        let mut keys: Vec<&String> = unit.opts.keys().collect();
+        keys.sort();
+        if let Some(key) = keys.into_iter().find(|key| !known.contains(&key.as_str())) {
+            let suggestions = util::close_matches(key, known);
+            return Err(FormattingError::UnknownOption(key.clone(), suggestions));
+        }
+    }
+    Ok(())
+}
+
+/// Adds the suggestions the `Fmt` can give to an unknown sub-`Fmt` or option
+/// error that came out of formatting it.
+fn suggest(root: &dyn Fmt, name: &[String], e: FormattingError) -> FormattingError {
+    match e {
+        FormattingError::UnknownFmt(ref full_name, ref suggestions)
+            if suggestions.is_empty() && *full_name == util::join_name(name) =>
+        {
+            FormattingError::UnknownFmt(full_name.clone(), suggest_sub_names(root, name))
+        }
+        FormattingError::UnknownOption(ref option, ref suggestions) if suggestions.is_empty() => {
+            let suggestions = root
+                .known_options()
+                .map_or_else(Vec::new, |known| util::close_matches(option, known));
+            FormattingError::UnknownOption(option.clone(), suggestions)
+        }
+        e => e,
+    }
+}
+
+/// Finds the first segment of the name that the `Fmt` has no sub-`Fmt` for,
+/// and gives the full names with the close matches in its place.
+fn suggest_sub_names(root: &dyn Fmt, name: &[String]) -> Vec<String> {
+    for i in 1..name.len() {
+        let names = match root.sub_names(&name[1..i]) {
+            Some(names) => names,
+            None => break,
+        };
+        if !names.contains(&name[i]) {
+            return util::close_matches(&name[i], &names)
+                .into_iter()
+                .map(|close| {
+                    let mut path = name[..i].to_vec();
+                    path.push(close);
+                    util::join_name(&path)
+                })
+                .collect();
+        }
+    }
+    Vec::new()
+}
+
+/// Evaluates the arguments and options of a placeholder with the table and
+/// the context it's being formatted with.
+struct TableEvaluator<'t, 'c, 'f: 'c, T: FormatTable + ?Sized + 't> {
+    table: &'t T,
+    context: &'c Context<'f>,
+    depth: u8,
+}
+
+impl<'t, 'c, 'f, T: FormatTable + ?Sized> Evaluator for TableEvaluator<'t, 'c, 'f, T> {
+    fn evaluate(&self, piece: &Piece) -> Result<String, FormattingError> {
+        format_one(self.table, piece, self.context, None, self.depth)
+    }
+
+    fn lookup<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        self.table.get_fmt(name)
     }
 }
 
@@ -358,11 +945,12 @@ impl<'a, T: ?Sized + 'a> Borrow<T> for BoxOrRef<'a, T> {
 #[derive(Debug, PartialEq)]
 pub enum SingleFmtError {
     /// Returned if a `Fmt` receives a flag it doesn't know how to handle.
-    /// It's not actually used by the `impl`s for the standard types, but you
-    /// can use it if you wish to be strict. Contains the erroneous flag.
+    /// The `impl`s for the standard types only do this in strict mode, by
+    /// declaring their flags with `Fmt::known_flags`, but you can use it if
+    /// you wish to be strict. Contains the erroneous flag.
     UnknownFlag(char),
     /// Returned if a `Fmt` receives an option it doesn't know how to handle.
-    /// Again, standard types do not do this, they are not strict. Contains the
+    /// Again, standard types only do this in strict mode. Contains the
     /// erroneous option.
     UnknownOption(String),
     /// Returned when a given option (stored in the first field) contains an
@@ -376,6 +964,13 @@ pub enum SingleFmtError {
     /// Returned when a `Fmt`does not contain a requested sub-`Fmt`. Contains
     /// the full path to the child format unit.
     UnknownSubfmt(String),
+    /// Returned when a `Fmt` that uses arguments receives too few or too
+    /// many of them. Contains the full path to the format unit and the number
+    /// of arguments it received.
+    InvalidArgumentCount(String, usize),
+    /// Returned when an argument has a value the `Fmt` cannot use. Contains
+    /// the full path to the format unit and the erroneous argument.
+    InvalidArgument(String, String),
 }
 
 /// Any error that can happen during formatting.
@@ -391,32 +986,80 @@ pub enum FormattingError {
     /// Returned if a placeholder is not terminated. Contains the erroneous
     /// input.
     UnterminatedPlaceholder(String),
+    /// Returned if a placeholder contains a malformed expression, or an
+    /// expression followed by arguments. Contains the erroneous input.
+    InvalidExpression(String),
+    /// Returned if a closing delimiter appears outside of any placeholder
+    /// without being escaped. Contains the erroneous input.
+    UnmatchedClosingDelimiter(String),
     // Errors from single Fmts.
     /// A `SingleFmtError::UnknownFlag` is propagated as this.
     UnknownFlag(char),
-    /// A `SingleFmtError::UnknownOption` is propagated as this.
-    UnknownOption(String),
+    /// A `SingleFmtError::UnknownOption` is propagated as this. Also contains
+    /// the options close to the unknown one that the `Fmt` knows, if it says
+    /// which ones it does (see `Fmt::known_options`). The suggestions are
+    /// new in 0.3; before that, this only contained the option.
+    UnknownOption(String, Vec<String>),
     /// A `SingleFmtError::InvalidOptionValue` is propagated as this.
     InvalidOptionValue(String, String),
     /// A `SingleFmtError::NamespaceOnlyFmt` is propagated as this.
     NamespaceOnlyFmt(String),
+    /// A `SingleFmtError::InvalidArgumentCount` is propagated as this.
+    InvalidArgumentCount(String, usize),
+    /// A `SingleFmtError::InvalidArgument` is propagated as this.
+    InvalidArgument(String, String),
+    // Filter errors.
+    /// Returned when a placeholder requests a filter that is not registered.
+    /// Contains the name of the filter.
+    UnknownFilter(String),
+    /// Returned when a filter fails. Contains the name of the filter and the
+    /// message it failed with.
+    FilterFailed(String, String),
+    // Template errors.
+    /// Returned when an include refers to a template that is not registered.
+    /// Contains the name of the template.
+    UnknownTemplate(String),
+    /// Returned when includes nest deeper than
+    /// `templates::MAX_INCLUDE_DEPTH`, which usually means that a template
+    /// includes itself. Contains the name of the template whose include
+    /// went over the limit.
+    IncludeDepthExceeded(String),
+    // Value errors.
+    /// Returned when a `Value` that is expected to be a number isn't one.
+    /// Contains the value.
+    NotANumber(String),
+    // Expression errors.
+    /// Returned when an operand of an expression has the wrong type, like a
+    /// string or a boolean in arithmetic. Contains the expression and the
+    /// offending value.
+    TypeMismatch(String, String),
+    /// Returned when an expression divides by zero. Contains the expression.
+    DivisionByZero(String),
     // General errors.
     /// Returned when a requested `Fmt` does not exist (or cannot be created)
     /// in the format table. A `SingleFmtError::UnknownSubfmt` is also
-    /// propagated as this. Contains the full path to the failed format unit.
-    UnknownFmt(String),
+    /// propagated as this. Contains the full path to the failed format unit,
+    /// and the full paths close to it that do exist, if the table (or the
+    /// `Fmt` with the sub-`Fmt`s) can tell which ones do (see
+    /// `FormatTable::names` and `Fmt::sub_names`). The suggestions are new
+    /// in 0.3; before that, this only contained the path.
+    UnknownFmt(String, Vec<String>),
 }
 
 impl From<SingleFmtError> for FormattingError {
     fn from(err: SingleFmtError) -> Self {
         match err {
             SingleFmtError::UnknownFlag(c) => FormattingError::UnknownFlag(c),
-            SingleFmtError::UnknownOption(s) => FormattingError::UnknownOption(s),
+            SingleFmtError::UnknownOption(s) => FormattingError::UnknownOption(s, Vec::new()),
             SingleFmtError::InvalidOptionValue(opt, val) => {
                 FormattingError::InvalidOptionValue(opt, val)
             }
             SingleFmtError::NamespaceOnlyFmt(s) => FormattingError::NamespaceOnlyFmt(s),
-            SingleFmtError::UnknownSubfmt(s) => FormattingError::UnknownFmt(s),
+            SingleFmtError::UnknownSubfmt(s) => FormattingError::UnknownFmt(s, Vec::new()),
+            SingleFmtError::InvalidArgumentCount(s, n) => {
+                FormattingError::InvalidArgumentCount(s, n)
+            }
+            SingleFmtError::InvalidArgument(s, arg) => FormattingError::InvalidArgument(s, arg),
         }
     }
 }
@@ -427,6 +1070,10 @@ impl From<ParseError> for FormattingError {
             ParseError::EmptyNameSegment(s) => FormattingError::EmptyName(s),
             ParseError::UnterminatedArgumentList(s) => FormattingError::UnterminatedArgumentList(s),
             ParseError::UnterminatedPlaceholder(s) => FormattingError::UnterminatedPlaceholder(s),
+            ParseError::InvalidExpression(s) => FormattingError::InvalidExpression(s),
+            ParseError::UnmatchedClosingDelimiter(s) => {
+                FormattingError::UnmatchedClosingDelimiter(s)
+            }
         }
     }
 }
@@ -444,12 +1091,47 @@ impl<'a, T: Borrow<dyn Fmt + 'a>> Fmt for T {
     ) -> Result<String, SingleFmtError> {
         self.borrow().format(full_name, name, args, flags, options)
     }
+
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        self.borrow().format_values(full_name, name, args, flags, options)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        self.borrow().known_flags()
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        self.borrow().known_options()
+    }
+
+    fn sub_names(&self, name: &[String]) -> Option<Vec<String>> {
+        self.borrow().sub_names(name)
+    }
+
+    fn sub_known_flags(&self, name: &[String]) -> Option<&'static [char]> {
+        self.borrow().sub_known_flags(name)
+    }
+
+    fn sub_known_options(&self, name: &[String]) -> Option<&'static [&'static str]> {
+        self.borrow().sub_known_options(name)
+    }
 }
 
 impl<'a, T: FormatTable> FormatTable for &'a T {
     fn get_fmt<'b, 'c>(&'b self, name: &'c str) -> Option<BoxOrRef<'b, dyn Fmt>> {
         (*self).get_fmt(name)
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        (*self).names()
+    }
 }
 
 /* ---------- implementations of FormatTable for standard types ---------- */
@@ -458,12 +1140,20 @@ impl<B: Borrow<dyn Fmt>> FormatTable for HashMap<String, B> {
     fn get_fmt<'a, 'b>(&'a self, name: &'b str) -> Option<BoxOrRef<'a, dyn Fmt>> {
         self.get(name).map(|b| BoxOrRef::Ref(b.borrow()))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(self.keys().cloned().collect())
+    }
 }
 
 impl<'a, B: Borrow<dyn Fmt>> FormatTable for HashMap<&'a str, B> {
     fn get_fmt<'b, 'c>(&'b self, name: &'c str) -> Option<BoxOrRef<'b, dyn Fmt>> {
         self.get(name).map(|r| BoxOrRef::Ref(r.borrow()))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(self.keys().map(|key| key.to_string()).collect())
+    }
 }
 
 impl<B: Borrow<dyn Fmt>> FormatTable for Vec<B> {
@@ -478,6 +1168,27 @@ impl<B: Borrow<dyn Fmt>> FormatTable for Vec<B> {
             None
         }
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some((0..self.len()).map(|index| index.to_string()).collect())
+    }
+}
+
+/// Puts together the names of several tables, or gives `None` if none of
+/// them can tell its names.
+pub(crate) fn merge_names<I>(names: I) -> Option<Vec<String>>
+where
+    I: IntoIterator<Item = Option<Vec<String>>>,
+{
+    let mut res: Option<Vec<String>> = None;
+    for names in names.into_iter().flatten() {
+        res.get_or_insert_with(Vec::new).extend(names);
+    }
+    if let Some(res) = res.as_mut() {
+        res.sort();
+        res.dedup();
+    }
+    res
 }
 
 impl<A, B> FormatTable for (A, B)
@@ -488,6 +1199,10 @@ where
     fn get_fmt<'a, 'b>(&'a self, name: &'b str) -> Option<BoxOrRef<'a, dyn Fmt>> {
         self.0.get_fmt(name).or_else(|| self.1.get_fmt(name))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        merge_names(vec![self.0.names(), self.1.names()])
+    }
 }
 
 impl<A, B, C> FormatTable for (A, B, C)
@@ -502,6 +1217,10 @@ where
             .or_else(|| self.1.get_fmt(name))
             .or_else(|| self.2.get_fmt(name))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        merge_names(vec![self.0.names(), self.1.names(), self.2.names()])
+    }
 }
 
 impl<A, B, C, D> FormatTable for (A, B, C, D)
@@ -518,6 +1237,10 @@ where
             .or_else(|| self.2.get_fmt(name))
             .or_else(|| self.3.get_fmt(name))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        merge_names(vec![self.0.names(), self.1.names(), self.2.names(), self.3.names()])
+    }
 }
 
 impl<A, B, C, D, E> FormatTable for (A, B, C, D, E)
@@ -536,6 +1259,16 @@ where
             .or_else(|| self.3.get_fmt(name))
             .or_else(|| self.4.get_fmt(name))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        merge_names(vec![
+            self.0.names(),
+            self.1.names(),
+            self.2.names(),
+            self.3.names(),
+            self.4.names(),
+        ])
+    }
 }
 
 impl<A, B, C, D, E, F> FormatTable for (A, B, C, D, E, F)
@@ -556,6 +1289,17 @@ where
             .or_else(|| self.4.get_fmt(name))
             .or_else(|| self.5.get_fmt(name))
     }
+
+    fn names(&self) -> Option<Vec<String>> {
+        merge_names(vec![
+            self.0.names(),
+            self.1.names(),
+            self.2.names(),
+            self.3.names(),
+            self.4.names(),
+            self.5.names(),
+        ])
+    }
 }
 
 /* ---------- implementations of Fmt for standard types ---------- */
@@ -594,6 +1338,14 @@ impl Fmt for bool {
         util::apply_common_options(&mut res, options)?;
         Ok(res)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::BOOL_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
 }
 
 /// This instance has no special flags.
@@ -614,6 +1366,14 @@ impl Fmt for char {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -644,6 +1404,14 @@ impl Fmt for f32 {
         util::apply_common_options(&mut res, options)?;
         Ok(res)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::FLOAT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -673,6 +1441,14 @@ impl Fmt for f64 {
         util::apply_common_options(&mut res, options)?;
         Ok(res)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::FLOAT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -700,6 +1476,14 @@ impl Fmt for i8 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -727,6 +1511,14 @@ impl Fmt for i16 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -754,6 +1546,14 @@ impl Fmt for i32 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -781,6 +1581,14 @@ impl Fmt for i64 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -808,6 +1616,14 @@ impl Fmt for i128 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -835,6 +1651,14 @@ impl Fmt for isize {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance has no special flags.
@@ -855,6 +1679,14 @@ impl<'a> Fmt for &'a str {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
 }
 
 /// This instance has no special flags.
@@ -875,6 +1707,14 @@ impl Fmt for String {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(&[])
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::COMMON_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -904,6 +1744,14 @@ impl Fmt for u8 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -933,6 +1781,14 @@ impl Fmt for u16 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -962,6 +1818,14 @@ impl Fmt for u32 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -991,6 +1855,14 @@ impl Fmt for u64 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -1020,6 +1892,14 @@ impl Fmt for u128 {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /// This instance is aware of the following flags:
@@ -1049,6 +1929,14 @@ impl Fmt for usize {
         util::apply_common_options(&mut s, options)?;
         Ok(s)
     }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        Some(util::INT_FLAGS)
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        Some(util::NUMERIC_OPTIONS)
+    }
 }
 
 /* ---------- tests for Fmts ---------- */
@@ -1064,7 +1952,7 @@ mod fmt_tests {
         test unknown_fmt() {
             let table: HashMap<&str, &Fmt> = HashMap::new();
             let s = table.format("i = {i}");
-            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("i".to_string()))));
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("i".to_string(), vec![]))));
         }
 
         test unknown_fmt_nested() {
@@ -1072,7 +1960,7 @@ mod fmt_tests {
             let mut table: HashMap<&str, &Fmt> = HashMap::new();
             table.insert("i", &i);
             let s = table.format("{i.a}");
-            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("i.a".to_string()))));
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("i.a".to_string(), vec![]))));
         }
 
         test integers_simple_1() {
@@ -1303,6 +2191,463 @@ mod fmt_tests {
             assert_that!(&s.as_str(), eq("12345"));
         }
 
+        test multiline_width() {
+            let string = "ab\nc\n\ndefg";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &string);
+            let s = table.format("{s::width=c4}|{s::width=l3}").unwrap();
+            assert_that!(&s.as_str(), eq(" ab \n c  \n    \ndefg|ab \nc  \n   \ndefg"));
+        }
+
+        test multiline_truncate() {
+            let string = "abcd\nef";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &string);
+            let s = table.format("{s::truncate=l3}").unwrap();
+            assert_that!(&s.as_str(), eq("bcd\nef"));
+        }
+
+        test block_width() {
+            let string = "a\nbbb";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &string);
+            let s = table.format("{s::width=c7:block}").unwrap();
+            assert_that!(&s.as_str(), eq("  a    \n  bbb  "));
+        }
+
+        test block_before_width() {
+            let string = "a\nbbb";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &string);
+            let s = table.format("{s::block:width=r5}").unwrap();
+            assert_that!(&s.as_str(), eq("  a  \n  bbb"));
+        }
+
+        test block_wider_than_width() {
+            let string = "a\nbbbbb";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &string);
+            let s = table.format("{s::width=r3:block}").unwrap();
+            assert_that!(&s.as_str(), eq("a    \nbbbbb"));
+        }
+
+    }
+
+    test_suite! {
+        name escaping;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+        use {Context, FormatTable, Fmt, FormattingError};
+        use escape::Escaper;
+
+        test explicit() {
+            let s = "a&b";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s}, {s::escape=html}, {s::escape=url}").unwrap();
+            assert_that!(&s.as_str(), eq("a&b, a&amp;b, a%26b"));
+        }
+
+        test after_common_options() {
+            let s = "it's";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s::width=l6:escape=shell}").unwrap();
+            assert_that!(&s.as_str(), eq("'it'\\''s  '"));
+        }
+
+        test default_escaper() {
+            let s = "\"q\"";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let context = Context { escaper: Some(Escaper::Json), ..Context::default() };
+            let s = table.format_with("\"{s}\", {s::escape=none}, {s::escape=csv}", &context).unwrap();
+            assert_that!(&s.as_str(), eq("\"\\\"q\\\"\", \"q\", \"\"\"q\"\"\""));
+        }
+
+        test unknown_escaper() {
+            let s = "a";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s::escape=xml}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidOptionValue(
+                "escape".to_string(), "xml".to_string()))));
+        }
+
+    }
+
+    test_suite! {
+        name filters;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+        use {Context, FormatTable, Fmt, FormattingError};
+        use escape::Escaper;
+        use filter::FilterRegistry;
+
+        test chain_order() {
+            let mut filters = FilterRegistry::new();
+            filters.register("upper", |s, _| Ok(s.to_uppercase()));
+            filters.register("exclaim", |s, _| Ok(format!("{}!", s)));
+            let s = "hi";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let context = Context { filters: Some(&filters), ..Context::default() };
+            let s = table.format_with("{s::filter=upper, exclaim:width=r3}", &context).unwrap();
+            assert_that!(&s.as_str(), eq(" HI!"));
+        }
+
+        test before_escaping() {
+            let mut filters = FilterRegistry::new();
+            filters.register("tag", |s, _| Ok(format!("<{}>", s)));
+            let s = "a";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let context = Context {
+                escaper: Some(Escaper::Html),
+                filters: Some(&filters),
+                ..Context::default()
+            };
+            let s = table.format_with("{s::filter=tag}", &context).unwrap();
+            assert_that!(&s.as_str(), eq("&lt;a&gt;"));
+        }
+
+        test no_registry() {
+            let s = "a";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s::filter=shout}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFilter("shout".to_string()))));
+        }
+
+        test pipes() {
+            let s = "a_long_string";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s | upper | truncate(r6) | width(c8)}").unwrap();
+            assert_that!(&s.as_str(), eq(" A_LONG "));
+        }
+
+        test literal_pipes() {
+            let s = "abc";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s::opt=|}{s:x |}{s::opt=a |}").unwrap();
+            assert_that!(&s.as_str(), eq("abcabcabc"));
+        }
+
+        test pipes_order_matters() {
+            let s = "abc";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("[{s | width(r5) | truncate(r4)}], [{s | truncate(r4) | width(r5)}]")
+                .unwrap();
+            assert_that!(&s.as_str(), eq("[  ab], [  abc]"));
+        }
+
+        test pipes_after_options() {
+            let mut filters = FilterRegistry::new();
+            filters.register("wrap", |s, args| Ok(format!("{}{}{}", args[0], s, args[1])));
+            let i = 10;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("i", &i);
+            let context = Context { filters: Some(&filters), ..Context::default() };
+            let s = table.format_with("{i:xp:width=l5 | wrap([, ]) | upper}", &context).unwrap();
+            assert_that!(&s.as_str(), eq("[0XA  ]"));
+        }
+
+        test failing_builtin() {
+            let s = "a";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("s", &s);
+            let s = table.format("{s | width(x)}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidOptionValue(
+                "width".to_string(), "x".to_string()))));
+        }
+
+    }
+
+    test_suite! {
+        name wrapping;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+        use {FormatTable, Fmt, FormattingError};
+
+        test simple() {
+            let text = "aaa bbb   ccc\tddd eee";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("t", &text);
+            let s = table.format("{t::wrap=8}").unwrap();
+            assert_that!(&s.as_str(), eq("aaa bbb\nccc ddd\neee"));
+        }
+
+        test long_words() {
+            let text = "a verylongword b";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("t", &text);
+            let s = table.format("{t::wrap=4}").unwrap();
+            assert_that!(&s.as_str(), eq("a\nverylongword\nb"));
+        }
+
+        test keeps_line_breaks() {
+            let text = "aaa\nbbb ccc";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("t", &text);
+            let s = table.format("{t::wrap=20:prefix=> }").unwrap();
+            assert_that!(&s.as_str(), eq("aaa\n> bbb ccc"));
+        }
+
+        test column_is_detected() {
+            let text = "aaa bbb ccc";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("t", &text);
+            let s = table.format("x\n12345 {t::wrap=12:indent=6}").unwrap();
+            assert_that!(&s.as_str(), eq("x\n12345 aaa\n      bbb\n      ccc"));
+        }
+
+        test explicit_column() {
+            let text = "aaa bbb ccc";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("t", &text);
+            let s = table.format("12345 {t::wrap=10:column=0}").unwrap();
+            assert_that!(&s.as_str(), eq("12345 aaa bbb\nccc"));
+        }
+
+        test any_fmt() {
+            let b = true;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("b", &b);
+            let s = table.format("{b::wrap=zz}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidOptionValue(
+                "wrap".to_string(), "zz".to_string()))));
+        }
+
+    }
+
+    test_suite! {
+        name expressions;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+        use {FormatTable, Fmt, FormattingError};
+
+        test percentage() {
+            let used = 3;
+            let total = 8;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("used", &used);
+            table.insert("total", &total);
+            let s = table.format("{=used / total * 100 ::prec=1}%");
+            assert_that!(&s, eq(Ok("37.5%".to_string())));
+        }
+
+        test integers_and_booleans() {
+            let errors = 2;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("errors", &errors);
+            let s = table.format("{=errors * 8:x}, {= errors > 0}, {=errors == 0:y}");
+            assert_that!(&s, eq(Ok("10, true, no".to_string())));
+        }
+
+        test options_and_filters() {
+            let a = 1.5;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("a", &a);
+            let s = table.format("[{=a * 2 ::width=r4}] [{=a > 1 | upper}]");
+            assert_that!(&s, eq(Ok("[   3] [TRUE]".to_string())));
+        }
+
+        test as_argument() {
+            let errors = 0;
+            let mut table: HashMap<&str, &dyn Fmt> = ::functions::table();
+            table.insert("errors", &errors);
+            let s = table.format("{if{{=errors > 0}:failed:ok}}");
+            assert_that!(&s, eq(Ok("ok".to_string())));
+        }
+
+        test errors() {
+            let name = "Bob";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("name", &name);
+            let s = table.format("{=name + 1}");
+            assert_that!(&s, eq(Err(FormattingError::TypeMismatch(
+                "name + 1".to_string(), "Bob".to_string()))));
+            let s = table.format("{=missing + 1}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("missing".to_string(), vec![]))));
+            let s = table.format("{=name +}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidExpression("{=name +}".to_string()))));
+            let s = table.format("{=name + 1{x}}");
+            assert_that!(&s, eq(Err(FormattingError::InvalidExpression(
+                "{=name + 1{x}}".to_string()))));
+        }
+
+        test names_with_operators() {
+            let name = "Bob";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("user-name", &name);
+            table.insert("a!", &name);
+            table.insert("user name", &name);
+            table.insert("=x", &name);
+            let s = table.format("{user-name} {a!} {user name} {user\\-name} {\\=x}");
+            assert_that!(&s, eq(Ok("Bob Bob Bob Bob Bob".to_string())));
+        }
+
+    }
+
+    test_suite! {
+        name strict_mode;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+        use adapters::FnFmt;
+        use tables::{Mounted, Namespace};
+        use {Context, FormatTable, Fmt, FormattingError};
+
+        fn strict() -> Context<'static> {
+            Context { strict: true, ..Context::default() }
+        }
+
+        test unknown_flags_and_options() {
+            let i = 10;
+            let s = "abc";
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("i", &i);
+            table.insert("s", &s);
+            let res = table.format_with("{i:xq}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownFlag('q'))));
+            let res = table.format_with("{s:x}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownFlag('x'))));
+            let res = table.format_with("{s::prec=2}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownOption("prec".to_string(), vec![]))));
+            let res = table.format_with("{i:q:zz=1:widht=r5}", &Context::default());
+            assert_that!(&res, eq(Ok("10".to_string())));
+        }
+
+        test known_flags_and_options() {
+            let i = 10;
+            let f = 0.25;
+            let b = true;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("i", &i);
+            table.insert("f", &f);
+            table.insert("b", &b);
+            let res = table.format_with(
+                "{i:+px:width=r6:round=up:prec=-1} {f:e:prec=1} {b:Y:escape=html:wrap=5}",
+                &strict());
+            assert_that!(&res, eq(Ok(" +0x10 3e-1 Y".to_string())));
+        }
+
+        test expressions() {
+            let i = 10;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("i", &i);
+            let res = table.format_with("{=i * 2:x}", &strict());
+            assert_that!(&res, eq(Ok("14".to_string())));
+            let res = table.format_with("{=i > 2:e}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownFlag('e'))));
+        }
+
+        test dotted_names() {
+            let mut inner: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            inner.insert("b", Box::new(1));
+            let mut table: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            table.insert("a", Box::new(Namespace(inner)));
+            let res = table.format_with("{a.b:zz}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownFlag('z'))));
+            let res = table.format_with("{a.b::widht=3}", &strict());
+            let suggestions = vec!["width".to_string()];
+            assert_that!(&res, eq(Err(FormattingError::UnknownOption("widht".to_string(), suggestions))));
+            let res = table.format_with("{a.b:x}", &strict());
+            assert_that!(&res, eq(Ok("1".to_string())));
+            let res = table.format_with("{a.c:zz}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownFmt("a.c".to_string(), vec!["a.b".to_string()]))));
+            let table = Mounted::new("a", table);
+            let res = table.format_with("{a.a.b:zz}", &strict());
+            assert_that!(&res, eq(Err(FormattingError::UnknownFlag('z'))));
+        }
+
+        test undeclared() {
+            let f = FnFmt::new(|_, _, _, _| Ok("f".to_string()));
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("f", &f);
+            let res = table.format_with("{f:q:z=1}", &strict());
+            assert_that!(&res, eq(Ok("f".to_string())));
+        }
+
+    }
+
+    test_suite! {
+        name suggestions;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+        use adapters::{FnFmt, FnTable};
+        use tables::Namespace;
+        use {util, Context, FormatTable, Fmt, FormattingError, SingleFmtError};
+
+        fn strings(names: &[&str]) -> Vec<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        }
+
+        test close_matches() {
+            assert_that!(&util::edit_distance("hostnmae", "hostname"), eq(1));
+            assert_that!(&util::edit_distance("abc", "ca"), eq(3));
+            assert_that!(&util::edit_distance("", "abc"), eq(3));
+            assert_that!(&util::edit_distance("kitten", "sitting"), eq(3));
+            let candidates = ["port", "host", "hostname", "hosts", "ports"];
+            let matches = util::close_matches("hots", &candidates);
+            assert_that!(&matches, eq(strings(&["host", "hosts"])));
+            assert_that!(&util::close_matches("hostnmae", &candidates), eq(strings(&["hostname"])));
+            assert_that!(&util::close_matches("x", &candidates), eq(Vec::<String>::new()));
+        }
+
+        test names() {
+            let i = 1;
+            let mut first: HashMap<&str, &dyn Fmt> = HashMap::new();
+            first.insert("user", &i);
+            let mut second: HashMap<String, &dyn Fmt> = HashMap::new();
+            second.insert("users".to_string(), &i);
+            second.insert("usrs".to_string(), &i);
+            let tables = (first, second);
+            let s = tables.format("{usr}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt(
+                "usr".to_string(), strings(&["user", "usrs"])))));
+            let s = tables.format("{usr.name}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt(
+                "usr.name".to_string(), strings(&["user", "usrs"])))));
+            let table = FnTable::new(|_| None);
+            let s = table.format("{usr}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("usr".to_string(), vec![]))));
+        }
+
+        test sub_names() {
+            let mut inner: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            inner.insert("port", Box::new(1));
+            let mut middle: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            middle.insert("db", Box::new(Namespace(inner)));
+            let mut table: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            table.insert("app", Box::new(Namespace(middle)));
+            let s = table.format("{app.db.prot}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt(
+                "app.db.prot".to_string(), strings(&["app.db.port"])))));
+            let s = table.format("{app.bd.port}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt(
+                "app.bd.port".to_string(), strings(&["app.db"])))));
+            let s = table.format("{app.db.port.x}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt(
+                "app.db.port.x".to_string(), vec![]))));
+        }
+
+        test options() {
+            let i = 1;
+            let f = FnFmt::new(|_, _, _, _| Err(SingleFmtError::UnknownOption("prex".to_string())));
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("i", &i);
+            table.insert("f", &f);
+            let context = Context { strict: true, ..Context::default() };
+            let s = table.format_with("{i::rond=up}", &context);
+            assert_that!(&s, eq(Err(FormattingError::UnknownOption(
+                "rond".to_string(), strings(&["round"])))));
+            let s = table.format("{f}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownOption("prex".to_string(), vec![]))));
+        }
+
     }
 
     test_suite! {
@@ -1410,7 +2755,8 @@ mod table_tests {
             let j = 2;
             let table: Vec<&Fmt> = vec![&i, &j];
             let err = table.format("{10}").expect_err("Unexpectedly found a fmt");
-            assert_that!(&err, eq(FormattingError::UnknownFmt("10".to_string())));
+            let suggestions = vec!["0".to_string(), "1".to_string()];
+            assert_that!(&err, eq(FormattingError::UnknownFmt("10".to_string(), suggestions)));
         }
 
         test unknown_fmt_2() {
@@ -1418,7 +2764,7 @@ mod table_tests {
             let j = 2;
             let table: Vec<&Fmt> = vec![&i, &j];
             let err = table.format("{-3}").expect_err("Unexpectedly found a fmt");
-            assert_that!(&err, eq(FormattingError::UnknownFmt("-3".to_string())));
+            assert_that!(&err, eq(FormattingError::UnknownFmt("-3".to_string(), vec![])));
         }
 
         test boring() {
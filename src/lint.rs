@@ -0,0 +1,306 @@
+//! Checking format strings for likely mistakes.
+//!
+//! `check` parses a format string and, besides reporting where it fails to
+//! parse, looks for the things that parse fine but probably don't do what
+//! was meant: flags and options that the type of a placeholder ignores,
+//! escapes of characters that don't need escaping, and separators outside
+//! of placeholders, which are silently dropped from the output.
+//! ```
+//! use std::collections::HashMap;
+//! use pfmt::lint::{check, Kind, Position, Warning};
+//! use pfmt::syntax::Syntax;
+//!
+//! let mut declarations = HashMap::new();
+//! declarations.insert("port".to_string(), Kind::Int);
+//! let warnings = check("port: {port:x:widht=r5}", &Syntax::default(), &declarations);
+//! assert_eq!(warnings, Ok(vec![
+//!     (Position { line: 1, column: 5 }, Warning::UnescapedSeparator),
+//!     (Position { line: 1, column: 7 },
+//!      Warning::UnknownOption("port".to_string(), "widht".to_string())),
+//! ]));
+//! ```
+
+use std::collections::HashMap;
+
+use parse::{next_token, parse_with_offsets, Piece, Token};
+use syntax::Syntax;
+use util;
+use FormattingError;
+
+/// A type of the values a placeholder refers to, which decides the flags and
+/// options it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Bool,
+    Char,
+    /// `f32` or `f64`.
+    Float,
+    /// Any signed integer type.
+    Int,
+    /// Any unsigned integer type.
+    Uint,
+    /// `String` or `&str`.
+    Str,
+}
+
+impl Kind {
+    /// Looks up a kind by its name: `bool`, `char`, `float`, `int`, `uint` or
+    /// `string`.
+    pub fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "bool" => Some(Kind::Bool),
+            "char" => Some(Kind::Char),
+            "float" => Some(Kind::Float),
+            "int" => Some(Kind::Int),
+            "uint" => Some(Kind::Uint),
+            "string" => Some(Kind::Str),
+            _ => None,
+        }
+    }
+
+    /// The flags the `Fmt`s of this kind are aware of.
+    pub fn flags(self) -> &'static [char] {
+        match self {
+            Kind::Bool => util::BOOL_FLAGS,
+            Kind::Char | Kind::Str => &[],
+            Kind::Float => util::FLOAT_FLAGS,
+            Kind::Int | Kind::Uint => util::INT_FLAGS,
+        }
+    }
+
+    /// The options the `Fmt`s of this kind recognise.
+    pub fn options(self) -> &'static [&'static str] {
+        match self {
+            Kind::Bool | Kind::Char | Kind::Str => util::COMMON_OPTIONS,
+            Kind::Float | Kind::Int | Kind::Uint => util::NUMERIC_OPTIONS,
+        }
+    }
+}
+
+/// A place in a format string. Both the line and the column start from 1,
+/// and the column is counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Something in a format string that is most likely a mistake.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A placeholder of a declared kind has a flag the kind doesn't know.
+    /// Contains the name of the placeholder and the flag.
+    UnknownFlag(String, char),
+    /// A placeholder of a declared kind has an option the kind doesn't know.
+    /// Contains the name of the placeholder and the option.
+    UnknownOption(String, String),
+    /// A character outside of placeholders is escaped, but would be taken
+    /// literally anyway. Contains the character.
+    UselessEscape(char),
+    /// The format string ends with the escape character, which escapes
+    /// nothing and is dropped.
+    DanglingEscape,
+    /// A raw block contains the escape character, which is kept as is there.
+    EscapeInRawBlock,
+    /// A separator outside of placeholders, which separates literals and
+    /// doesn't show up in the output.
+    UnescapedSeparator,
+}
+
+/// Checks a format string written in the given syntax. The placeholders
+/// named in `declarations` (by their full names, with the path separator
+/// written as a dot) are checked for the flags and options of their kinds.
+///
+/// Returns the warnings in the order they appear in, or the position of the
+/// first syntax error along with the error. The warnings about placeholders
+/// in arguments and options are given the position of the top-level
+/// placeholder.
+pub fn check(
+    input: &str,
+    syntax: &Syntax,
+    declarations: &HashMap<String, Kind>,
+) -> Result<Vec<(Position, Warning)>, (Position, FormattingError)> {
+    let pieces = match parse_with_offsets(input, syntax) {
+        Ok(pieces) => pieces,
+        Err((offset, e)) => return Err((position(input, offset), e.into())),
+    };
+    let mut checker = Checker {
+        syntax,
+        declarations,
+        warnings: Vec::new(),
+    };
+    for (i, (offset, piece)) in pieces.iter().enumerate() {
+        let end = pieces.get(i + 1).map_or(input.len(), |(end, _)| *end);
+        let source = &input[*offset..end];
+        match piece {
+            Piece::Literal(s) if source.starts_with(&syntax.open[..]) => {
+                if s.contains(syntax.escape) {
+                    let pos = source.find(syntax.escape).unwrap_or(0);
+                    checker.warn(offset + pos, Warning::EscapeInRawBlock);
+                }
+            }
+            Piece::Literal(_) => checker.literal(source, *offset),
+            piece => checker.piece(piece, *offset),
+        }
+    }
+    Ok(checker
+        .warnings
+        .into_iter()
+        .map(|(offset, warning)| (position(input, offset), warning))
+        .collect())
+}
+
+fn position(input: &str, offset: usize) -> Position {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+struct Checker<'a> {
+    syntax: &'a Syntax,
+    declarations: &'a HashMap<String, Kind>,
+    warnings: Vec<(usize, Warning)>,
+}
+
+impl<'a> Checker<'a> {
+    fn warn(&mut self, offset: usize, warning: Warning) {
+        self.warnings.push((offset, warning));
+    }
+
+    /// Checks the source of a top-level literal, which starts at the given
+    /// offset.
+    fn literal(&mut self, source: &str, offset: usize) {
+        let syntax = self.syntax;
+        let mut pos = 0;
+        while let Some((token, len)) = next_token(syntax, &source[pos..]) {
+            match token {
+                Token::Open | Token::Close(_) => break,
+                Token::Char(ch) if ch == syntax.separator && !syntax.literal_separators => {
+                    self.warn(offset + pos, Warning::UnescapedSeparator);
+                }
+                Token::Char(_) => {}
+                Token::Escaped(s) => {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (None, _) => self.warn(offset + pos, Warning::DanglingEscape),
+                        (Some(ch), None) if !self.needs_escaping(ch) => {
+                            self.warn(offset + pos, Warning::UselessEscape(ch));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            pos += len;
+        }
+    }
+
+    /// Whether escaping the character makes any difference outside of
+    /// placeholders.
+    fn needs_escaping(&self, ch: char) -> bool {
+        let syntax = self.syntax;
+        ch == syntax.escape
+            || (ch == syntax.separator && !syntax.literal_separators)
+            || syntax.open.starts_with(ch)
+            || syntax.close.starts_with(ch)
+    }
+
+    /// Checks a placeholder or an expression, along with everything nested
+    /// in it.
+    fn piece(&mut self, piece: &Piece, offset: usize) {
+        let opts = match piece {
+            Piece::Placeholder(name, args, flags, opts, _) => {
+                let name = util::join_name(name);
+                if let Some(kind) = self.declarations.get(&name) {
+                    let mut seen = Vec::new();
+                    for flag in flags {
+                        if !kind.flags().contains(flag) && !seen.contains(flag) {
+                            seen.push(*flag);
+                            self.warn(offset, Warning::UnknownFlag(name.clone(), *flag));
+                        }
+                    }
+                    for key in sorted_keys(opts) {
+                        if !kind.options().contains(&key.as_str()) {
+                            self.warn(offset, Warning::UnknownOption(name.clone(), key.clone()));
+                        }
+                    }
+                }
+                for arg in args {
+                    self.piece(arg, offset);
+                }
+                opts
+            }
+            Piece::Expression(_, _, opts, _) => opts,
+            _ => return,
+        };
+        for key in sorted_keys(opts) {
+            self.piece(&opts[key], offset);
+        }
+    }
+}
+
+fn sorted_keys(opts: &HashMap<String, Piece>) -> Vec<&String> {
+    let mut keys: Vec<&String> = opts.keys().collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name checks;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use lint::*;
+        use syntax::Syntax;
+        use FormattingError;
+
+        fn at(line: usize, column: usize, warning: Warning) -> (Position, Warning) {
+            (Position { line, column }, warning)
+        }
+
+        test declared_kinds() {
+            let mut declarations = HashMap::new();
+            declarations.insert("a.b".to_string(), Kind::Float);
+            declarations.insert("c".to_string(), Kind::Bool);
+            let s = "{a.b:ezz:prec=2:x=1}\n  {f{{c:Y:q}}::k={a.b:b}}{a:z}";
+            let res = check(s, &Syntax::default(), &declarations);
+            assert_that!(&res, eq(Ok(vec![
+                at(1, 1, Warning::UnknownFlag("a.b".to_string(), 'z')),
+                at(1, 1, Warning::UnknownOption("a.b".to_string(), "x".to_string())),
+                at(2, 3, Warning::UnknownOption("c".to_string(), "q".to_string())),
+                at(2, 3, Warning::UnknownFlag("a.b".to_string(), 'b')),
+            ])));
+        }
+
+        test escapes_and_separators() {
+            let s = "a\\b\\{\\:\\\\é: {x}\\ {!\\!}{# \\ #}\\";
+            let res = check(s, &Syntax::default(), &HashMap::new());
+            assert_that!(&res, eq(Ok(vec![
+                at(1, 2, Warning::UselessEscape('b')),
+                at(1, 11, Warning::UnescapedSeparator),
+                at(1, 16, Warning::UselessEscape(' ')),
+                at(1, 20, Warning::EscapeInRawBlock),
+                at(1, 30, Warning::DanglingEscape),
+            ])));
+            let syntax = Syntax::default().literal_separators(true);
+            let res = check("a: \\:", &syntax, &HashMap::new());
+            assert_that!(&res, eq(Ok(vec![at(1, 4, Warning::UselessEscape(':'))])));
+            let res = check("\\~", &Syntax::default(), &HashMap::new());
+            assert_that!(&res, eq(Ok(vec![at(1, 1, Warning::UselessEscape('~'))])));
+        }
+
+        test syntax_errors() {
+            let res = check("ab\nc {d{{f:", &Syntax::default(), &HashMap::new());
+            let e = FormattingError::UnterminatedPlaceholder("{f:".to_string());
+            assert_that!(&res, eq(Err((Position { line: 2, column: 6 }, e))));
+            let res = check("{a}\n\n}", &Syntax::default(), &HashMap::new());
+            let e = FormattingError::UnmatchedClosingDelimiter("{a}\n\n}".to_string());
+            assert_that!(&res, eq(Err((Position { line: 3, column: 1 }, e))));
+        }
+
+    }
+}
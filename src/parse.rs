@@ -1,18 +1,45 @@
 use std::collections::HashMap;
 
-const ESCAPE: char = '\\';
-const SETOPT: char = '=';
-const FIELD_SEPARATOR: char = ':';
-const OPENING_BRACKET: char = '{';
-const CLOSING_BRACKET: char = '}';
-const DOT: char = '.';
+use expr::{self, Expression};
+use syntax::Syntax;
+
+pub(crate) const INCLUDE: char = '>';
+pub(crate) const COMMENT: char = '#';
+pub(crate) const RAW: char = '!';
+pub(crate) const EXPRESSION: char = '=';
+pub(crate) const TRIM: char = '~';
+pub(crate) const PIPE: char = '|';
+pub(crate) const OPENING_PAREN: char = '(';
+pub(crate) const CLOSING_PAREN: char = ')';
+pub(crate) const ARG_SEPARATOR: char = ',';
 const MAX_RECURSION_DEPTH: u8 = 100;
 
-/// Either a literal string, or a placecholder.
-#[derive(Debug, PartialEq)]
+/// Either a literal string, or a placecholder, or a placeholder with an
+/// expression in place of the name.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Piece {
     Literal(String),
-    Placeholder(Vec<String>, Vec<Piece>, Vec<char>, HashMap<String, Piece>),
+    Placeholder(
+        Vec<String>,
+        Vec<Piece>,
+        Vec<char>,
+        HashMap<String, Piece>,
+        Vec<FilterCall>,
+    ),
+    Expression(Expression, Vec<char>, HashMap<String, Piece>, Vec<FilterCall>),
+    /// An include of a named template.
+    Include(String),
+    /// A comment, which is kept only so that the format string can be
+    /// reproduced.
+    Comment(String),
+}
+
+/// A filter applied to a placeholder with the pipe syntax, along with its
+/// arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterCall {
+    pub name: String,
+    pub args: Vec<String>,
 }
 
 /// Errors that occur during parsing a format string.
@@ -21,138 +48,410 @@ pub enum ParseError {
     EmptyNameSegment(String),
     UnterminatedArgumentList(String),
     UnterminatedPlaceholder(String),
+    InvalidExpression(String),
+    UnmatchedClosingDelimiter(String),
+}
+
+impl ParseError {
+    /// The input the error was found in: the rest of the format string from
+    /// the offending placeholder on, or the whole format string.
+    fn input(&self) -> &str {
+        match self {
+            ParseError::EmptyNameSegment(s)
+            | ParseError::UnterminatedArgumentList(s)
+            | ParseError::UnterminatedPlaceholder(s)
+            | ParseError::InvalidExpression(s)
+            | ParseError::UnmatchedClosingDelimiter(s) => s,
+        }
+    }
 }
 
-pub fn parse(input: &str) -> Result<Vec<Piece>, ParseError> {
+/// The smallest unit of a format string, as far as the delimiters and the
+/// escaping are concerned.
+pub(crate) enum Token<'a> {
+    /// A character that is not escaped, and is not a part of a delimiter.
+    Char(char),
+    /// An escaped character or delimiter, which is always taken literally.
+    Escaped(&'a str),
+    Open,
+    /// A closing delimiter, and whether it's preceded by a trim marker. The
+    /// trim marker only counts with whitespace in front of it, which is a
+    /// part of the token as well.
+    Close(bool),
+}
+
+/// Reads the token at the start of the input, returning it along with its
+/// length in bytes.
+pub(crate) fn next_token<'a>(syntax: &Syntax, input: &'a str) -> Option<(Token<'a>, usize)> {
+    let ch = input.chars().next()?;
+    if ch == syntax.escape {
+        let rest = &input[ch.len_utf8()..];
+        let len = if rest.starts_with(&syntax.open[..]) {
+            syntax.open.len()
+        } else if rest.starts_with(&syntax.close[..]) {
+            syntax.close.len()
+        } else {
+            rest.chars().next().map_or(0, char::len_utf8)
+        };
+        Some((Token::Escaped(&rest[..len]), ch.len_utf8() + len))
+    } else if input.starts_with(&syntax.open[..]) {
+        Some((Token::Open, syntax.open.len()))
+    } else if ch.is_whitespace() && bare_trim_marker(&input[ch.len_utf8()..], syntax).is_some() {
+        Some((Token::Close(true), ch.len_utf8() + TRIM.len_utf8() + syntax.close.len()))
+    } else if input.starts_with(&syntax.close[..]) {
+        Some((Token::Close(false), syntax.close.len()))
+    } else {
+        Some((Token::Char(ch), ch.len_utf8()))
+    }
+}
+
+/// If the input starts with a trim marker right before the closing
+/// delimiter, with no whitespace in front of it, returns the length of both.
+fn bare_trim_marker(input: &str, syntax: &Syntax) -> Option<usize> {
+    if input.starts_with(TRIM) && input[TRIM.len_utf8()..].starts_with(&syntax.close[..]) {
+        Some(TRIM.len_utf8() + syntax.close.len())
+    } else {
+        None
+    }
+}
+
+/// If the input starts with a closing delimiter, returns its length, and
+/// whether it's preceded by a trim marker.
+fn closing_delimiter(input: &str, syntax: &Syntax) -> Option<(usize, bool)> {
+    match next_token(syntax, input) {
+        Some((Token::Close(trim), len)) => Some((len, trim)),
+        _ => None,
+    }
+}
+
+/// Whitespace trimming requested by a placeholder, `{~name ~}`, for the
+/// literals around it.
+#[derive(Debug, Clone, Copy, Default)]
+struct Trim {
+    before: bool,
+    after: bool,
+}
+
+pub fn parse(input: &str, syntax: &Syntax) -> Result<Vec<Piece>, ParseError> {
+    match parse_with_offsets(input, syntax) {
+        Ok(pieces) => Ok(pieces.into_iter().map(|(_, piece)| piece).collect()),
+        Err((_, e)) => Err(e),
+    }
+}
+
+/// Like `parse`, but pairs every top-level piece with the byte offset it
+/// starts at. On failure, gives the offset of the offending placeholder (the
+/// innermost one) or closing delimiter along with the error.
+pub(crate) fn parse_with_offsets(
+    input: &str,
+    syntax: &Syntax,
+) -> Result<Vec<(usize, Piece)>, (usize, ParseError)> {
+    let full_input = input;
     let mut input = input;
     let mut res = Vec::new();
+    let mut trim_next = false;
     while !input.is_empty() {
-        let (piece, rest) = parse_piece(input, 0, false)?;
+        let offset = full_input.len() - input.len();
+        if input.starts_with(&syntax.open[..]) {
+            let (piece, trim, rest) = match parse_placeholder(input, syntax, 0) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    let offset = full_input.len() - e.input().len();
+                    return Err((offset, e));
+                }
+            };
+            if trim.before {
+                trim_last_literals(&mut res);
+            }
+            trim_next = trim.after;
+            input = rest;
+            res.push((offset, piece));
+            continue;
+        }
+        let (piece, rest) = parse_literal(input, syntax, false, false, true);
+        if rest.len() == input.len() {
+            // Only a closing delimiter stops a literal right away.
+            let e = ParseError::UnmatchedClosingDelimiter(full_input.to_string());
+            return Err((offset, e));
+        }
         input = rest;
-        res.push(piece);
+        match piece {
+            Piece::Literal(ref s) if trim_next => {
+                let s = s.trim_start();
+                if s.is_empty() {
+                    // The whitespace may go on in the next literal.
+                    continue;
+                }
+                res.push((offset, Piece::Literal(s.to_string())));
+            }
+            piece => res.push((offset, piece)),
+        }
+        trim_next = false;
     }
     Ok(res)
 }
 
-fn parse_piece(
-    input: &str,
+/// Trims the whitespace at the end of the literals the pieces end with,
+/// dropping the ones with nothing left, up to the first one that isn't all
+/// whitespace.
+fn trim_last_literals(pieces: &mut Vec<(usize, Piece)>) {
+    while let Some((_, Piece::Literal(s))) = pieces.last_mut() {
+        let len = s.trim_end().len();
+        s.truncate(len);
+        if !s.is_empty() {
+            break;
+        }
+        pieces.pop();
+    }
+}
+
+fn parse_piece<'a>(
+    input: &'a str,
+    syntax: &Syntax,
     recursion_depth: u8,
     new_arglist: bool,
-) -> Result<(Piece, &str), ParseError> {
-    let mut iter = input.chars();
-    match iter.next() {
-        Some(OPENING_BRACKET) => parse_placeholder(input, recursion_depth),
-        _ => parse_literal(input, new_arglist),
+    in_option: bool,
+) -> Result<(Piece, &'a str), ParseError> {
+    if input.starts_with(&syntax.open[..]) {
+        // Trim markers only matter around top-level placeholders.
+        let (piece, _, rest) = parse_placeholder(input, syntax, recursion_depth)?;
+        Ok((piece, rest))
+    } else {
+        Ok(parse_literal(input, syntax, new_arglist, in_option, false))
     }
 }
 
-fn parse_literal(input: &str, new_arglist: bool) -> Result<(Piece, &str), ParseError> {
-    let mut prev = None;
+fn parse_literal<'a>(
+    input: &'a str,
+    syntax: &Syntax,
+    new_arglist: bool,
+    in_option: bool,
+    top_level: bool,
+) -> (Piece, &'a str) {
     let mut literal = String::new();
-    for (i, ch) in input.char_indices() {
-        if i == 0 && ch == FIELD_SEPARATOR {
-            if new_arglist {
-                return Ok((Piece::Literal(literal), input));
-            } else {
-                continue;
-            }
-        }
-        if ch == FIELD_SEPARATOR && prev != Some(ESCAPE) {
-            return Ok((Piece::Literal(literal), &input[i..]));
-        }
-        if ch == OPENING_BRACKET && prev != Some(ESCAPE) {
-            return Ok((Piece::Literal(literal), &input[i..]));
-        }
-        if ch == CLOSING_BRACKET && prev != Some(ESCAPE) {
-            return Ok((Piece::Literal(literal), &input[i..]));
+    let mut pos = 0;
+    let separates = !(top_level && syntax.literal_separators);
+    if separates && input.starts_with(syntax.separator) {
+        if new_arglist {
+            return (Piece::Literal(literal), input);
         }
-        if ch == FIELD_SEPARATOR || ch == OPENING_BRACKET || ch == CLOSING_BRACKET {
-            literal.push(ch);
-            prev = Some(ch);
-        } else if ch == ESCAPE && prev == Some(ESCAPE) {
-            literal.push(ch);
-            prev = None;
-        } else if ch != ESCAPE {
-            literal.push(ch);
-            prev = Some(ch);
-        } else {
-            prev = Some(ch);
+        pos = syntax.separator.len_utf8();
+    }
+    while let Some((token, len)) = next_token(syntax, &input[pos..]) {
+        match token {
+            Token::Open | Token::Close(_) => break,
+            Token::Char(ch) if separates && ch == syntax.separator => break,
+            Token::Char(ch) if in_option && ch.is_whitespace() => {
+                if starts_filter_chain(&input[pos..]) {
+                    break;
+                }
+                literal.push(ch);
+            }
+            Token::Char(ch) => literal.push(ch),
+            Token::Escaped(s) => literal.push_str(s),
         }
+        pos += len;
     }
-    Ok((Piece::Literal(literal), ""))
+    (Piece::Literal(literal), &input[pos..])
 }
 
-fn parse_placeholder(input: &str, recursion_depth: u8) -> Result<(Piece, &str), ParseError> {
+fn parse_placeholder<'a>(
+    input: &'a str,
+    syntax: &Syntax,
+    recursion_depth: u8,
+) -> Result<(Piece, Trim, &'a str), ParseError> {
     if recursion_depth > MAX_RECURSION_DEPTH {
-        return grab_until_terminator(input);
+        let (piece, rest) = grab_until_terminator(input, syntax)?;
+        return Ok((piece, Trim::default(), rest));
     }
     let first_input = input;
-    let input = &input[1..]; // skip the {
-    let (name, input) = extract_name(first_input, input)?;
-    let (arguments, input) = extract_arguments(first_input, input, recursion_depth)?;
-    let (flags, input) = extract_flags(first_input, input)?;
-    let (options, input) = extract_options(first_input, input, recursion_depth)?;
-    let input = extract_placeholder_terminator(first_input, input)?;
-    Ok((Piece::Placeholder(name, arguments, flags, options), input))
+    let mut input = &input[syntax.open.len()..];
+    let trim_before = input.starts_with(TRIM);
+    if trim_before {
+        input = &input[TRIM.len_utf8()..];
+    }
+    let (piece, input) = if input.starts_with(INCLUDE) {
+        parse_include(first_input, &input[1..], syntax)?
+    } else if input.starts_with(COMMENT) {
+        let (text, rest) = grab_block(first_input, &input[1..], COMMENT, syntax)?;
+        (Piece::Comment(text.to_string()), rest)
+    } else if input.starts_with(RAW) {
+        let (text, rest) = grab_block(first_input, &input[1..], RAW, syntax)?;
+        (Piece::Literal(text.to_string()), rest)
+    } else if input.starts_with(EXPRESSION) {
+        let input = &input[EXPRESSION.len_utf8()..];
+        let name_len = raw_name_len(input, syntax);
+        parse_expression_placeholder(first_input, input, name_len, syntax, recursion_depth)?
+    } else {
+        parse_named_placeholder(first_input, input, syntax, recursion_depth)?
+    };
+    let (input, trim_after) = extract_placeholder_terminator(first_input, input, syntax)?;
+    let trim = Trim {
+        before: trim_before,
+        after: trim_after,
+    };
+    Ok((piece, trim, input))
 }
 
-fn extract_name<'a, 'b>(
-    orig_input: &'a str,
+/// Parses the usual placeholder, up to its closing delimiter.
+fn parse_named_placeholder<'a>(
+    first_input: &str,
+    input: &'a str,
+    syntax: &Syntax,
+    recursion_depth: u8,
+) -> Result<(Piece, &'a str), ParseError> {
+    let (name, input) = extract_name(first_input, input, syntax)?;
+    let (arguments, input) = extract_arguments(first_input, input, syntax, recursion_depth)?;
+    let (flags, input) = extract_flags(first_input, input, syntax)?;
+    let (options, input) = extract_options(first_input, input, syntax, recursion_depth)?;
+    let (filters, input) = extract_filters(first_input, input, syntax)?;
+    Ok((
+        Piece::Placeholder(name, arguments, flags, options, filters),
+        input,
+    ))
+}
+
+/// The length of the part of the placeholder that holds its expression,
+/// escapes included.
+fn raw_name_len(input: &str, syntax: &Syntax) -> usize {
+    let mut pos = 0;
+    while let Some((token, len)) = next_token(syntax, &input[pos..]) {
+        match token {
+            Token::Open | Token::Close(_) => break,
+            Token::Char(ch) if ch == syntax.separator => break,
+            Token::Char(ch) if ch.is_whitespace() && starts_filter_chain(&input[pos..]) => break,
+            _ => pos += len,
+        }
+    }
+    pos
+}
+
+/// Parses a placeholder with an expression, `{=a + b}`, from right after the
+/// `=`. Such a placeholder has no arguments, but can have flags, options and
+/// filters, which apply to the value of the expression.
+fn parse_expression_placeholder<'a>(
+    first_input: &str,
+    input: &'a str,
+    name_len: usize,
+    syntax: &Syntax,
+    recursion_depth: u8,
+) -> Result<(Piece, &'a str), ParseError> {
+    let expression = match expr::parse_expression(&input[..name_len]) {
+        Some(expression) => expression,
+        None => return Err(ParseError::InvalidExpression(first_input.to_string())),
+    };
+    let input = &input[name_len..];
+    if input.starts_with(&syntax.open[..]) {
+        return Err(ParseError::InvalidExpression(first_input.to_string()));
+    }
+    let (flags, input) = extract_flags(first_input, input, syntax)?;
+    let (options, input) = extract_options(first_input, input, syntax, recursion_depth)?;
+    let (filters, input) = extract_filters(first_input, input, syntax)?;
+    Ok((Piece::Expression(expression, flags, options, filters), input))
+}
+
+/// Parses an include, `{>name}`. The name is taken literally, up to the
+/// closing delimiter (or the trim marker in front of it, along with the
+/// whitespace the marker needs).
+fn parse_include<'b>(
+    first_input: &str,
+    input: &'b str,
+    syntax: &Syntax,
+) -> Result<(Piece, &'b str), ParseError> {
+    match input.find(&syntax.close[..]) {
+        Some(end) => {
+            let name = &input[..end];
+            let end = match name.char_indices().rev().nth(1) {
+                Some((pos, ch)) if ch.is_whitespace() && name.ends_with(TRIM) => pos,
+                _ => end,
+            };
+            let name = trim_name(first_input, &input[..end])?;
+            Ok((Piece::Include(name), &input[end..]))
+        }
+        None => Err(ParseError::UnterminatedPlaceholder(first_input.to_string())),
+    }
+}
+
+/// Grabs the contents of a comment or a raw block, up to the marker followed
+/// by the closing delimiter. There's no escaping inside.
+fn grab_block<'b>(
+    first_input: &str,
     input: &'b str,
+    marker: char,
+    syntax: &Syntax,
+) -> Result<(&'b str, &'b str), ParseError> {
+    let mut from = 0;
+    while let Some(pos) = input[from..].find(marker) {
+        let pos = from + pos;
+        let rest = &input[pos + marker.len_utf8()..];
+        if closing_delimiter(rest, syntax).is_some() || bare_trim_marker(rest, syntax).is_some() {
+            return Ok((&input[..pos], rest));
+        }
+        from = pos + marker.len_utf8();
+    }
+    Err(ParseError::UnterminatedPlaceholder(first_input.to_string()))
+}
+
+fn extract_name<'b>(
+    orig_input: &str,
+    input: &'b str,
+    syntax: &Syntax,
 ) -> Result<(Vec<String>, &'b str), ParseError> {
     let mut name = Vec::new();
     let mut segment = String::new();
-    let mut prev = None;
-    for (i, ch) in input.char_indices() {
-        if ch == FIELD_SEPARATOR || ch == OPENING_BRACKET || ch == CLOSING_BRACKET {
-            if prev == Some(ESCAPE) {
-                segment.push(ch);
-                prev = Some(ch);
-            } else {
+    let mut pos = 0;
+    while let Some((token, len)) = next_token(syntax, &input[pos..]) {
+        match token {
+            Token::Open | Token::Close(_) => break,
+            Token::Char(ch) if ch == syntax.separator => break,
+            Token::Char(ch) if ch.is_whitespace() && starts_filter_chain(&input[pos..]) => break,
+            Token::Char(ch) if ch == syntax.path_separator => {
                 name.push(segment);
-                let mut res = Vec::with_capacity(name.len());
-                for segm in name.iter() {
-                    res.push(trim_name(orig_input, &segm)?);
-                }
-                return Ok((res, &input[i..]));
+                segment = String::new();
             }
-        } else if ch == DOT && prev != Some(ESCAPE) {
-            name.push(segment);
-            segment = String::new();
-            prev = Some(ch);
-        } else if ch == ESCAPE && prev == Some(ESCAPE) {
-            segment.push(ESCAPE);
-            prev = None;
-        } else if ch == ESCAPE {
-            prev = Some(ch);
-        } else {
-            segment.push(ch);
-            prev = Some(ch);
+            Token::Char(ch) => segment.push(ch),
+            Token::Escaped(s) => segment.push_str(s),
         }
+        pos += len;
     }
-    Err(ParseError::UnterminatedPlaceholder(orig_input.to_string()))
+    if pos == input.len() {
+        return Err(ParseError::UnterminatedPlaceholder(orig_input.to_string()));
+    }
+    name.push(segment);
+    let mut res = Vec::with_capacity(name.len());
+    for segm in name.iter() {
+        res.push(trim_name(orig_input, &segm)?);
+    }
+    Ok((res, &input[pos..]))
 }
 
-fn extract_arguments<'a, 'b>(
-    first_input: &'a str,
+fn extract_arguments<'b>(
+    first_input: &str,
     input: &'b str,
+    syntax: &Syntax,
     recursion_depth: u8,
 ) -> Result<(Vec<Piece>, &'b str), ParseError> {
-    let mut iter = input.char_indices();
-    match iter.next() {
-        Some((_, OPENING_BRACKET)) => (),
-        Some((_, FIELD_SEPARATOR)) => return Ok((Vec::new(), input)),
-        Some((_, CLOSING_BRACKET)) => return Ok((Vec::new(), input)),
-        _ => panic!(
-            "Somehow dead code in 'extract_arguments' was reached on input: '{}'",
-            input
-        ),
-    }
-    let mut input = &input[1..];
+    if !input.starts_with(&syntax.open[..]) {
+        return Ok((Vec::new(), input));
+    }
+    let separator_len = syntax.separator.len_utf8();
+    let mut input = &input[syntax.open.len()..];
     let mut args = Vec::new();
     let mut first = true;
-    while input.chars().next() != Some(CLOSING_BRACKET) {
-        let (piece, rest) = parse_piece(input, recursion_depth + 1, first)?;
+    loop {
+        // The trim marker means nothing at the end of an argument list.
+        if let Some((len, _)) = closing_delimiter(input, syntax) {
+            return Ok((args, &input[len..]));
+        }
+        if !first
+            && input.starts_with(syntax.separator)
+            && input[separator_len..].starts_with(&syntax.open[..])
+        {
+            // The separator in front of a placeholder argument, otherwise it
+            // would be parsed as an empty literal argument.
+            input = &input[separator_len..];
+        }
+        let (piece, rest) = parse_piece(input, syntax, recursion_depth + 1, first, false)?;
         first = false;
         args.push(piece);
         input = rest;
@@ -162,139 +461,203 @@ fn extract_arguments<'a, 'b>(
             ));
         }
     }
-    Ok((args, &input[1..]))
 }
 
-fn extract_flags<'a, 'b>(
-    full_input: &'a str,
+fn extract_flags<'b>(
+    full_input: &str,
     input: &'b str,
+    syntax: &Syntax,
 ) -> Result<(Vec<char>, &'b str), ParseError> {
-    let mut iter = input.char_indices().peekable();
-    match iter.peek() {
-        Some((_, FIELD_SEPARATOR)) => {
-            iter.next();
-        }
-        Some((_, CLOSING_BRACKET)) => return Ok((Vec::new(), input)),
-        Some(_) => (),
-        None => return Err(ParseError::UnterminatedPlaceholder(full_input.to_string())),
+    let mut pos = 0;
+    if input.starts_with(syntax.separator) {
+        pos = syntax.separator.len_utf8();
+    } else if closing_delimiter(input, syntax).is_some() || starts_filter_chain(input) {
+        return Ok((Vec::new(), input));
     }
     let mut flags = Vec::new();
-    let mut prev = None;
-    for (i, ch) in iter {
-        if ch == FIELD_SEPARATOR && prev != Some(ESCAPE) {
-            return Ok((flags, &input[i..]));
-        } else if ch == FIELD_SEPARATOR && prev == Some(ESCAPE) {
-            flags.push(ch);
-            prev = Some(ch);
-        } else if ch == ESCAPE && prev == Some(ESCAPE) {
-            flags.push(ch);
-            prev = None;
-        } else if ch == CLOSING_BRACKET && prev != Some(ESCAPE) {
-            return Ok((flags, &input[i..]));
-        } else if ch != ESCAPE {
-            flags.push(ch);
-            prev = Some(ch);
-        } else {
-            prev = Some(ch);
+    while let Some((token, len)) = next_token(syntax, &input[pos..]) {
+        match token {
+            Token::Close(_) => return Ok((flags, &input[pos..])),
+            Token::Char(ch) if ch == syntax.separator => return Ok((flags, &input[pos..])),
+            Token::Char(ch) if ch.is_whitespace() && starts_filter_chain(&input[pos..]) => {
+                return Ok((flags, &input[pos..]));
+            }
+            Token::Char(ch) => flags.push(ch),
+            Token::Escaped(s) => flags.extend(s.chars()),
+            Token::Open => flags.extend(syntax.open.chars()),
         }
+        pos += len;
     }
-    if prev != Some(CLOSING_BRACKET) {
-        Err(ParseError::UnterminatedPlaceholder(full_input.to_string()))
-    } else {
-        Ok((flags, ""))
-    }
+    Err(ParseError::UnterminatedPlaceholder(full_input.to_string()))
 }
 
-fn extract_options<'a, 'b>(
-    full_input: &'a str,
+fn extract_options<'b>(
+    full_input: &str,
     input: &'b str,
+    syntax: &Syntax,
     recursion_depth: u8,
 ) -> Result<(HashMap<String, Piece>, &'b str), ParseError> {
-    let mut iter = input.char_indices();
-    match iter.next() {
-        Some((_, FIELD_SEPARATOR)) => (),
-        Some((_, CLOSING_BRACKET)) => return Ok((HashMap::new(), input)),
-        None => return Err(ParseError::UnterminatedPlaceholder(full_input.to_string())),
-        _ => panic!(
-            "Reached dead code in 'extract_options' on input '{}'",
-            full_input
-        ),
-    }
     let mut res: HashMap<String, Piece> = HashMap::new();
-    let mut prev = None;
+    if !input.starts_with(syntax.separator) {
+        return Ok((res, input));
+    }
+    let mut input = &input[syntax.separator.len_utf8()..];
     let mut name = String::new();
-    let mut input = input;
-    let mut end = 0;
-    loop {
-        let maybe_next = iter.next();
-        if maybe_next.is_none() {
-            break;
-        }
-        let (i, ch) = maybe_next.unwrap();
-        end = i;
-        if ch == CLOSING_BRACKET && prev != Some(ESCAPE) {
-            break;
-        } else if ch == FIELD_SEPARATOR && prev != Some(ESCAPE) {
-            input = &input[1..];
-            iter = input.char_indices();
-            name = String::new();
-            continue;
-        } else if ch == SETOPT && prev != Some(ESCAPE) {
-            name = trim_name(full_input, &name)?;
-            let (opt, rest) = parse_piece(&input[i + 1..], recursion_depth + 1, false)?;
-            res.insert(name, opt);
-            iter = rest.char_indices();
-            input = rest;
-            name = String::new();
-            continue;
-        } else if ch == ESCAPE && prev == Some(ESCAPE) {
-            name.push(ESCAPE);
-            prev = None;
-        } else if ch == ESCAPE && prev != Some(ESCAPE) {
-            prev = Some(ch);
-        } else {
-            name.push(ch);
-            prev = Some(ch);
+    while let Some((token, len)) = next_token(syntax, input) {
+        match token {
+            Token::Close(_) => break,
+            Token::Char(ch) if ch.is_whitespace() && starts_filter_chain(input) => break,
+            Token::Char(ch) if ch == syntax.separator => {
+                let key = name.trim();
+                if !key.is_empty() {
+                    res.insert(key.to_string(), Piece::Literal("".to_string()));
+                }
+                name = String::new();
+            }
+            Token::Char(ch) if ch == syntax.assign => {
+                let key = trim_name(full_input, &name)?;
+                let (opt, rest) =
+                    parse_piece(&input[len..], syntax, recursion_depth + 1, false, true)?;
+                res.insert(key, opt);
+                input = rest;
+                name = String::new();
+                continue;
+            }
+            Token::Char(ch) => name.push(ch),
+            Token::Escaped(s) => name.push_str(s),
+            Token::Open => name.push_str(&syntax.open),
         }
+        input = &input[len..];
     }
+    let name = name.trim();
     if !name.is_empty() {
-        res.insert(name, Piece::Literal("".to_string()));
+        res.insert(name.to_string(), Piece::Literal("".to_string()));
+    }
+    Ok((res, input))
+}
+
+fn extract_filters<'b>(
+    full_input: &str,
+    input: &'b str,
+    syntax: &Syntax,
+) -> Result<(Vec<FilterCall>, &'b str), ParseError> {
+    let mut filters = Vec::new();
+    if !starts_filter_chain(input) {
+        return Ok((filters, input));
+    }
+    let mut input = input.trim_start();
+    while input.starts_with(PIPE) {
+        let (filter, rest) = extract_filter(full_input, &input[1..], syntax)?;
+        filters.push(filter);
+        input = rest.trim_start();
+    }
+    Ok((filters, input))
+}
+
+fn extract_filter<'b>(
+    full_input: &str,
+    input: &'b str,
+    syntax: &Syntax,
+) -> Result<(FilterCall, &'b str), ParseError> {
+    let (name, mut input) = extract_filter_word(full_input, input, syntax)?;
+    let name = trim_name(full_input, &name)?;
+    let mut args = Vec::new();
+    if input.starts_with(OPENING_PAREN) {
+        input = &input[1..];
+        loop {
+            let (arg, rest) = extract_filter_word(full_input, input, syntax)?;
+            let mut iter = rest.chars();
+            match iter.next() {
+                Some(ARG_SEPARATOR) => args.push(arg.trim().to_string()),
+                Some(CLOSING_PAREN) => {
+                    if !(arg.trim().is_empty() && args.is_empty()) {
+                        args.push(arg.trim().to_string());
+                    }
+                    input = iter.as_str();
+                    break;
+                }
+                _ => return Err(ParseError::UnterminatedArgumentList(full_input.to_string())),
+            }
+            input = iter.as_str();
+        }
+    }
+    Ok((FilterCall { name, args }, input))
+}
+
+/// Whether the input starts with the pipe that begins a filter chain. Only a
+/// pipe with whitespace on both sides does, so that a pipe in a name, in the
+/// flags or in an option value is taken literally.
+fn starts_filter_chain(input: &str) -> bool {
+    let rest = input.trim_start();
+    rest.len() < input.len()
+        && rest.starts_with(PIPE)
+        && rest[PIPE.len_utf8()..].starts_with(char::is_whitespace)
+}
+
+/// Grabs a filter name or a filter argument, stopping at any character that
+/// has a meaning in a filter chain.
+fn extract_filter_word<'b>(
+    full_input: &str,
+    input: &'b str,
+    syntax: &Syntax,
+) -> Result<(String, &'b str), ParseError> {
+    let mut word = String::new();
+    let mut pos = 0;
+    while let Some((token, len)) = next_token(syntax, &input[pos..]) {
+        match token {
+            Token::Close(_)
+            | Token::Char(PIPE)
+            | Token::Char(OPENING_PAREN)
+            | Token::Char(CLOSING_PAREN)
+            | Token::Char(ARG_SEPARATOR) => return Ok((word, &input[pos..])),
+            Token::Char(ch) => word.push(ch),
+            Token::Escaped(s) => word.push_str(s),
+            Token::Open => word.push_str(&syntax.open),
+        }
+        pos += len;
     }
-    Ok((res, &input[end..]))
+    Err(ParseError::UnterminatedPlaceholder(full_input.to_string()))
 }
 
-fn extract_placeholder_terminator<'a, 'b>(
-    full_input: &'a str,
+/// Skips the closing delimiter, returning whether it had a trim marker. The
+/// end of a comment or a raw block sets the trim marker apart well enough,
+/// so right after one, it doesn't need whitespace in front of it.
+fn extract_placeholder_terminator<'b>(
+    full_input: &str,
     input: &'b str,
-) -> Result<&'b str, ParseError> {
-    match input.chars().next() {
-        Some(CLOSING_BRACKET) => Ok(&input[1..]),
-        _ => Err(ParseError::UnterminatedPlaceholder(full_input.to_string())),
+    syntax: &Syntax,
+) -> Result<(&'b str, bool), ParseError> {
+    if let Some(len) = bare_trim_marker(input, syntax) {
+        return Ok((&input[len..], true));
+    }
+    match closing_delimiter(input, syntax) {
+        Some((len, trim)) => Ok((&input[len..], trim)),
+        None => Err(ParseError::UnterminatedPlaceholder(full_input.to_string())),
     }
 }
 
-fn grab_until_terminator(input: &str) -> Result<(Piece, &str), ParseError> {
-    let mut prev = None;
+/// Takes a placeholder that is nested too deep literally, up to its closing
+/// delimiter.
+fn grab_until_terminator<'a>(input: &'a str, syntax: &Syntax) -> Result<(Piece, &'a str), ParseError> {
     let mut literal = String::new();
-    let mut balance = 1;
-    for (i, ch) in input.char_indices() {
-        if ch == OPENING_BRACKET && prev != Some(ESCAPE) {
-            balance += 1;
-            literal.push(ch);
-            prev = Some(ch);
-        } else if ch == CLOSING_BRACKET && prev != Some(ESCAPE) {
-            balance -= 1;
-            literal.push(ch);
-            prev = Some(ch);
-        } else if ch == ESCAPE && prev == Some(ESCAPE) {
-            literal.push(ch);
-            prev = None;
-        } else if ch != ESCAPE {
-            literal.push(ch);
-            prev = Some(ch);
+    let mut balance = 0;
+    let mut pos = 0;
+    while let Some((token, len)) = next_token(syntax, &input[pos..]) {
+        match token {
+            Token::Open => {
+                balance += 1;
+                literal.push_str(&syntax.open);
+            }
+            Token::Close(_) => {
+                balance -= 1;
+                literal.push_str(&input[pos..pos + len]);
+            }
+            Token::Char(ch) => literal.push(ch),
+            Token::Escaped(s) => literal.push_str(s),
         }
+        pos += len;
         if balance == 0 {
-            return Ok((Piece::Literal(literal), &input[i + 1..]));
+            return Ok((Piece::Literal(literal), &input[pos..]));
         }
     }
     Err(ParseError::UnterminatedPlaceholder(input.to_string()))
@@ -317,11 +680,12 @@ mod tests {
         use galvanic_assert::matchers::*;
 
         use parse::*;
+        use syntax::Syntax;
         use Piece::*;
 
         test literal() {
             let s = "asdf 1";
-            let res = parse(&s);
+            let res = parse(&s, &Syntax::default());
             let pieces = res.expect("Failed to get any pieces");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
@@ -330,7 +694,7 @@ mod tests {
 
         test single_placeholder_1() {
             let s = "a{b}c";
-            let res = parse(&s);
+            let res = parse(&s, &Syntax::default());
             let pieces = res.expect("Failed to get any pieces");
             assert_that!(&pieces.len(), eq(3));
             let a = &pieces[0];
@@ -342,13 +706,14 @@ mod tests {
                                             eq(vec!["b".to_string()]),
                                             eq(Vec::new()),
                                             eq(Vec::new()),
-                                            eq(HashMap::new())
+                                            eq(HashMap::new()),
+                                            eq(Vec::new())
             ]));
         }
 
         test single_placeholder_2() {
             let s = "a{b}";
-            let pieces = parse(&s).expect("Failed to get any pieces");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to get any pieces");
             assert_that!(&pieces.len(), eq(2));
             let a = &pieces[0];
             let b = &pieces[1];
@@ -357,13 +722,14 @@ mod tests {
                                             eq(vec!["b".to_string()]),
                                             eq(Vec::new()),
                                             eq(Vec::new()),
-                                            eq(HashMap::new())
+                                            eq(HashMap::new()),
+                                            eq(Vec::new())
             ]));
         }
 
         test several_placeholders() {
             let s = "a{b}c{d}";
-            let pieces = parse(&s).expect("Failed to get any pieces");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to get any pieces");
             assert_that!(&pieces.len(), eq(4));
             let a = &pieces[0];
             let b = &pieces[1];
@@ -374,27 +740,30 @@ mod tests {
                                             eq(vec!["b".to_string()]),
                                             eq(Vec::new()),
                                             eq(Vec::new()),
-                                            eq(HashMap::new())
+                                            eq(HashMap::new()),
+                                            eq(Vec::new())
             ]));
             assert_that!(&c, eq(Literal("c".to_string())));
             assert_that!(&d, has_structure!(Placeholder [
                                             eq(vec!["d".to_string()]),
                                             eq(Vec::new()),
                                             eq(Vec::new()),
-                                            eq(HashMap::new())
+                                            eq(HashMap::new()),
+                                            eq(Vec::new())
             ]));
         }
 
         test explicit_separator_before_literal() {
             let s = "{foobar}:asdf";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(2));
             let pl = &pieces[0];
             assert_that!(&pl,
                          eq(Placeholder(vec!["foobar".to_string()],
                          Vec::new(),
                          Vec::new(),
-                         HashMap::new()
+                         HashMap::new(),
+                         Vec::new()
                          )));
             let lit = &pieces[1];
             assert_that!(&lit, eq(Literal("asdf".to_string())));
@@ -402,7 +771,7 @@ mod tests {
 
         test escapes_in_literals() {
             let s = "a\\:b\\{c\\}d\\\\";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(&piece, eq(Literal("a:b{c}d\\".to_string())));
@@ -410,18 +779,19 @@ mod tests {
 
         test escapes_in_placeholder_names() {
             let s = "{fo\\:ob\\\\ar\\{\\}}";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(&piece, eq(Placeholder(vec!["fo:ob\\ar{}".to_string()],
                                                 Vec::new(),
                                                 Vec::new(),
-                                                HashMap::new())));
+                                                HashMap::new(),
+                                                Vec::new())));
         }
 
         test escapes_in_option_names() {
             let s = "{foobar::o\\:p\\{\\}t\\\\ion=1}";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(&piece, eq(Placeholder(vec!["foobar".to_string()],
@@ -433,12 +803,13 @@ mod tests {
                                                     let lit = Literal("1".to_string());
                                                     m.insert(s, lit);
                                                     m
-                                                })));
+                                                },
+                                                Vec::new())));
         }
 
         test multiple_options() {
             let s = "{foobar::a=a:b=b}";
-            let pieces = parse(&s).expect("Parse failed");
+            let pieces = parse(&s, &Syntax::default()).expect("Parse failed");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(piece, eq(Placeholder(vec!["foobar".to_string()],
@@ -451,7 +822,22 @@ mod tests {
                                         m.insert("a".to_string(), a);
                                         m.insert("b".to_string(), b);
                                         m
-                                    })));
+                                    },
+                                    Vec::new())));
+        }
+
+        test options_without_values() {
+            let s = "{foobar::a:b=b:c}";
+            let pieces = parse(s, &Syntax::default()).expect("Parse failed");
+            let mut m = HashMap::new();
+            m.insert("a".to_string(), Literal("".to_string()));
+            m.insert("b".to_string(), Literal("b".to_string()));
+            m.insert("c".to_string(), Literal("".to_string()));
+            assert_that!(&pieces, eq(vec![Placeholder(vec!["foobar".to_string()],
+                                                      Vec::new(),
+                                                      Vec::new(),
+                                                      m,
+                                                      Vec::new())]));
         }
 
     }
@@ -461,11 +847,12 @@ mod tests {
         use galvanic_assert::matchers::*;
 
         use parse::*;
+        use syntax::Syntax;
         use parse::ParseError::*;
 
         test unterminated_name() {
             let s = "12{asdf";
-            let err = parse(&s).expect_err("Parse succeeded");
+            let err = parse(&s, &Syntax::default()).expect_err("Parse succeeded");
             assert_that!(&err, has_structure!(
                     UnterminatedPlaceholder [eq("{asdf".to_string())]
                     ));
@@ -473,31 +860,31 @@ mod tests {
 
         test unterminated_arguments_list() {
             let s = "12{asdf{qq";
-            let err = parse(&s).expect_err("Parse succeeded");
+            let err = parse(&s, &Syntax::default()).expect_err("Parse succeeded");
             assert_that!(&err, eq(UnterminatedArgumentList("{asdf{qq".to_string())));
         }
 
         test unterminated_argument() {
             let s = "12{asdf{{a";
-            let err = parse(&s).expect_err("Parse succeeded");
+            let err = parse(&s, &Syntax::default()).expect_err("Parse succeeded");
             assert_that!(&err, eq(UnterminatedPlaceholder("{a".to_string())));
         }
 
         test no_closing_bracket_after_arguments() {
             let s = "{foobar{asdf}";
-            let err = parse(&s).expect_err("Parse succeeded");
+            let err = parse(&s, &Syntax::default()).expect_err("Parse succeeded");
             assert_that!(&err, eq(UnterminatedPlaceholder(s.to_string())));
         }
 
         test unterminated_flags() {
             let s = "{foobar:asdf";
-            let err = parse(&s).expect_err("Parse succeeded");
+            let err = parse(&s, &Syntax::default()).expect_err("Parse succeeded");
             assert_that!(&err, eq(UnterminatedPlaceholder(s.to_string())));
         }
 
         test unterminated_options() {
             let s= "{foobar::";
-            let err = parse(&s).expect_err("Parse succeeded");
+            let err = parse(&s, &Syntax::default()).expect_err("Parse succeeded");
             assert_that!(&err, eq(UnterminatedPlaceholder(s.to_string())));
         }
 
@@ -509,11 +896,12 @@ mod tests {
         use galvanic_assert::matchers::*;
 
         use parse::*;
+        use syntax::Syntax;
         use Piece::*;
 
         test single_argument() {
             let s = "{foobar{asdf}}";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(&piece, has_structure!(
@@ -521,13 +909,14 @@ mod tests {
                         any_value(),
                         eq(vec![Literal("asdf".to_string())]),
                         any_value(),
+                        any_value(),
                         any_value()
                     ]));
         }
 
         test two_literals() {
             let s = "{foobar{a:b}}";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(&piece, has_structure!(
@@ -535,13 +924,14 @@ mod tests {
                         any_value(),
                         eq(vec![Literal("a".to_string()), Literal("b".to_string())]),
                         any_value(),
+                        any_value(),
                         any_value()
                     ]));
         }
 
         test empty_arguments() {
             let s = "{foobar{::}}";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
             assert_that!(&piece, has_structure!(
@@ -552,16 +942,32 @@ mod tests {
                                 Literal("".to_string())
                         ]),
                         any_value(),
+                        any_value(),
                         any_value()
                     ]));
         }
 
+        test placeholders_after_separators() {
+            let s = "{foobar{a:{b}:{c}:d}}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            if let Placeholder(_, args, _, _, _) = &pieces[0] {
+                let placeholder = |name: &str| Placeholder(vec![name.to_string()], Vec::new(),
+                                                           Vec::new(), HashMap::new(), Vec::new());
+                assert_that!(args, eq(vec![Literal("a".to_string()),
+                                           placeholder("b"),
+                                           placeholder("c"),
+                                           Literal("d".to_string())]));
+            } else {
+                panic!("Not a placeholder: {:?}", pieces[0]);
+            }
+        }
+
         test full_literal() {
             let s = "{foobar{{baz{arg}flags:opt=1}}}";
-            let pieces = parse(&s).expect("Failed to parse");
+            let pieces = parse(&s, &Syntax::default()).expect("Failed to parse");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
-            if let Placeholder(_, args, _, _) = piece {
+            if let Placeholder(_, args, _, _, _) = piece {
                 assert_that!(&args.len(), eq(1));
                 assert_that!(&args[0], eq(Placeholder(vec!["baz".to_string()],
                                             vec![Literal("arg".to_string())],
@@ -571,7 +977,8 @@ mod tests {
                                                 let lit = Literal("1".to_string());
                                                 m.insert("opt".to_string(), lit);
                                                 m
-                                            })));
+                                            },
+                                            Vec::new())));
             } else {
                 panic!("Not a placeholder: {:?}", piece);
             }
@@ -579,16 +986,240 @@ mod tests {
 
     }
 
+    test_suite! {
+        name includes;
+        use galvanic_assert::matchers::*;
+
+        use parse::*;
+        use syntax::Syntax;
+        use Piece::*;
+
+        test include() {
+            let s = "a{>header}b{> foot.er }";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Literal("a".to_string()),
+                                          Include("header".to_string()),
+                                          Literal("b".to_string()),
+                                          Include("foot.er".to_string())]));
+        }
+
+        test errors() {
+            let s = "{>}";
+            let res = parse(s, &Syntax::default());
+            assert_that!(&res, eq(Err(ParseError::EmptyNameSegment(s.to_string()))));
+            let s = "{>a";
+            let res = parse(s, &Syntax::default());
+            assert_that!(&res, eq(Err(ParseError::UnterminatedPlaceholder(s.to_string()))));
+        }
+
+    }
+
+    test_suite! {
+        name blocks;
+        use galvanic_assert::matchers::*;
+
+        use parse::*;
+        use syntax::Syntax;
+        use Piece::*;
+
+        test comments_and_raw() {
+            let s = "a{# {b} :c #}d{!{e}:\\!}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Literal("a".to_string()),
+                                          Comment(" {b} :c ".to_string()),
+                                          Literal("d".to_string()),
+                                          Literal("{e}:\\".to_string())]));
+        }
+
+        test custom_delimiters() {
+            let syntax = Syntax::default().delimiters("<%", "%>").unwrap();
+            let s = "<%# a #%><%! <%b%> !%>";
+            let pieces = parse(s, &syntax).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Comment(" a ".to_string()),
+                                          Literal(" <%b%> ".to_string())]));
+        }
+
+        test unterminated() {
+            let s = "{#a}";
+            let res = parse(s, &Syntax::default());
+            assert_that!(&res, eq(Err(ParseError::UnterminatedPlaceholder(s.to_string()))));
+        }
+
+    }
+
+    test_suite! {
+        name trimming;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use parse::*;
+        use syntax::Syntax;
+        use Piece::*;
+
+        fn placeholder(name: &str, flags: Vec<char>) -> Piece {
+            Placeholder(vec![name.to_string()], vec![], flags, HashMap::new(), vec![])
+        }
+
+        test around_placeholders() {
+            let s = "a \n {~b ~} \n c {d:x ~}\n\n{~e}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Literal("a".to_string()),
+                                          placeholder("b", vec![]),
+                                          Literal("c ".to_string()),
+                                          placeholder("d", vec!['x']),
+                                          placeholder("e", vec![])]));
+        }
+
+        test around_other_pieces() {
+            let s = "a\n{~# c #~}\n{~>t ~} {!r!~}\n";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Literal("a".to_string()),
+                                          Comment(" c ".to_string()),
+                                          Include("t".to_string()),
+                                          Literal("r".to_string())]));
+        }
+
+        test marker_needs_whitespace() {
+            let s = " {a::prefix=~} {b~}{c:x~} ";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            let mut opts = HashMap::new();
+            opts.insert("prefix".to_string(), Literal("~".to_string()));
+            assert_that!(&pieces, eq(vec![Literal(" ".to_string()),
+                                          Placeholder(vec!["a".to_string()], vec![], vec![],
+                                                      opts, vec![]),
+                                          Literal(" ".to_string()),
+                                          placeholder("b~", vec![]),
+                                          placeholder("c", vec!['x', '~']),
+                                          Literal(" ".to_string())]));
+        }
+
+        test across_literals() {
+            let s = "a \n: \n {~b ~} : \n:c";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Literal("a".to_string()),
+                                          placeholder("b", vec![]),
+                                          Literal("c".to_string())]));
+        }
+
+        test escaped_marker() {
+            let s = " {a:\\~} ";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![Literal(" ".to_string()),
+                                          placeholder("a", vec!['~']),
+                                          Literal(" ".to_string())]));
+        }
+
+    }
+
+    test_suite! {
+        name filters;
+        use galvanic_assert::matchers::*;
+
+        use parse::*;
+        use syntax::Syntax;
+        use Piece::*;
+
+        fn call(name: &str, args: &[&str]) -> FilterCall {
+            FilterCall {
+                name: name.to_string(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+            }
+        }
+
+        test chain() {
+            let s = "{a | upper | truncate( r10 ) | join(\\,, \\|)}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces.len(), eq(1));
+            assert_that!(&pieces[0], has_structure!(
+                    Placeholder [
+                        eq(vec!["a".to_string()]),
+                        any_value(),
+                        any_value(),
+                        any_value(),
+                        eq(vec![call("upper", &[]),
+                                call("truncate", &["r10"]),
+                                call("join", &[",", "|"])])
+                    ]));
+        }
+
+        test after_flags_and_options() {
+            let s = "{a:xp:width=l5 | upper}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            if let Placeholder(_, _, flags, opts, filters) = &pieces[0] {
+                assert_that!(flags, eq(vec!['x', 'p']));
+                assert_that!(&opts["width"], eq(Literal("l5".to_string())));
+                assert_that!(filters, eq(vec![call("upper", &[])]));
+            } else {
+                panic!("Not a placeholder: {:?}", pieces[0]);
+            }
+        }
+
+        test empty_argument_list() {
+            let s = "{a | upper()}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces[0], has_structure!(
+                    Placeholder [any_value(), any_value(), any_value(), any_value(),
+                                 eq(vec![call("upper", &[])])]));
+        }
+
+        test escaped_pipe_in_name() {
+            let s = "{a\\|b}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces[0], has_structure!(
+                    Placeholder [eq(vec!["a|b".to_string()]), any_value(), any_value(),
+                                 any_value(), eq(Vec::new())]));
+        }
+
+        test pipes_without_whitespace() {
+            let s = "{a|b:x |:k=| y:j=a |b}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            if let Placeholder(name, _, flags, opts, filters) = &pieces[0] {
+                assert_that!(name, eq(vec!["a|b".to_string()]));
+                assert_that!(flags, eq(vec!['x', ' ', '|']));
+                assert_that!(&opts["k"], eq(Literal("| y".to_string())));
+                assert_that!(&opts["j"], eq(Literal("a |b".to_string())));
+                assert_that!(filters, eq(Vec::new()));
+            } else {
+                panic!("Not a placeholder: {:?}", pieces[0]);
+            }
+        }
+
+        test whitespace_before_chain() {
+            let s = "{a::k=x\\  | upper}";
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            if let Placeholder(_, _, _, opts, filters) = &pieces[0] {
+                assert_that!(&opts["k"], eq(Literal("x ".to_string())));
+                assert_that!(filters, eq(vec![call("upper", &[])]));
+            } else {
+                panic!("Not a placeholder: {:?}", pieces[0]);
+            }
+        }
+
+        test empty_filter_name() {
+            let s = "{a | }";
+            let res = parse(s, &Syntax::default());
+            assert_that!(&res, eq(Err(ParseError::EmptyNameSegment(s.to_string()))));
+        }
+
+        test unterminated_filter_arguments() {
+            let s = "{a | upper(x}";
+            let res = parse(s, &Syntax::default());
+            assert_that!(&res, eq(Err(ParseError::UnterminatedArgumentList(s.to_string()))));
+        }
+
+    }
+
     test_suite! {
         name names;
         use galvanic_assert::matchers::*;
 
         use parse::*;
+        use syntax::Syntax;
         use Piece::*;
 
         test several_segments() {
             let s = "{a.b.c}";
-            let res = parse(&s);
+            let res = parse(&s, &Syntax::default());
             let pieces = res.expect("Failed to get any pieces");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
@@ -597,19 +1228,20 @@ mod tests {
                         eq(vec!["a".to_string(), "b".to_string(), "c".to_string()]),
                         any_value(),
                         any_value(),
+                        any_value(),
                         any_value()
                     ]));
         }
 
         test empty_segment() {
             let s = "{a..c}";
-            let res = parse(&s);
+            let res = parse(&s, &Syntax::default());
             assert_that!(&res, eq(Err(ParseError::EmptyNameSegment("{a..c}".to_string()))));
         }
 
         test escapes_in_segments() {
             let s = "{a\\..b}";
-            let res = parse(&s);
+            let res = parse(&s, &Syntax::default());
             let pieces = res.expect("Failed to get any pieces");
             assert_that!(&pieces.len(), eq(1));
             let piece = &pieces[0];
@@ -618,10 +1250,81 @@ mod tests {
                         eq(vec!["a.".to_string(), "b".to_string()]),
                         any_value(),
                         any_value(),
+                        any_value(),
                         any_value()
                     ]));
         }
 
     }
 
+    test_suite! {
+        name custom_syntax;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use parse::*;
+        use syntax::Syntax;
+        use Piece::*;
+
+        test multichar_delimiters() {
+            let syntax = Syntax::default().delimiters("${", "}").unwrap();
+            let s = "{a ${b${${c}}:x:o=1}";
+            let pieces = parse(s, &syntax).expect("Failed to parse");
+            let mut opts = HashMap::new();
+            opts.insert("o".to_string(), Literal("1".to_string()));
+            assert_that!(&pieces, eq(vec![
+                Literal("{a ".to_string()),
+                Placeholder(vec!["b".to_string()],
+                            vec![Placeholder(vec!["c".to_string()], vec![], vec![],
+                                             HashMap::new(), vec![])],
+                            vec!['x'], opts, vec![])]));
+        }
+
+        test escaped_delimiters() {
+            let syntax = Syntax::default().delimiters("[[", "]]").unwrap().escape('^').unwrap();
+            let s = "^[[a^]] \\ [[b^]]]]";
+            let pieces = parse(s, &syntax).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![
+                Literal("[[a]] \\ ".to_string()),
+                Placeholder(vec!["b]]".to_string()], vec![], vec![], HashMap::new(), vec![])]));
+        }
+
+        test special_characters() {
+            let syntax = Syntax::default()
+                .separator(';')
+                .and_then(|s| s.path_separator('/'))
+                .and_then(|s| s.assign(':'))
+                .unwrap();
+            let s = "{a/b;x;o:1}";
+            let pieces = parse(s, &syntax).expect("Failed to parse");
+            let mut opts = HashMap::new();
+            opts.insert("o".to_string(), Literal("1".to_string()));
+            assert_that!(&pieces, eq(vec![
+                Placeholder(vec!["a".to_string(), "b".to_string()], vec![], vec!['x'],
+                            opts, vec![])]));
+        }
+
+        test literal_separators() {
+            let syntax = Syntax::default().literal_separators(true);
+            let s = ":a: \\:b{c{d:e}:x}:";
+            let pieces = parse(s, &syntax).expect("Failed to parse");
+            assert_that!(&pieces, eq(vec![
+                Literal(":a: :b".to_string()),
+                Placeholder(vec!["c".to_string()],
+                            vec![Literal("d".to_string()), Literal("e".to_string())],
+                            vec!['x'], HashMap::new(), vec![]),
+                Literal(":".to_string())]));
+            let pieces = parse(s, &Syntax::default()).expect("Failed to parse");
+            assert_that!(&pieces[0], eq(Literal("a".to_string())));
+        }
+
+        test unmatched_closing_delimiter() {
+            let s = "a}b";
+            let res = parse(s, &Syntax::default());
+            assert_that!(&res, eq(Err(ParseError::UnmatchedClosingDelimiter(s.to_string()))));
+            let res = parse("a\\}b", &Syntax::default());
+            assert_that!(&res, eq(Ok(vec![Literal("a}b".to_string())])));
+        }
+
+    }
 }
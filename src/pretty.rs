@@ -0,0 +1,294 @@
+//! Canonical form of format strings.
+//!
+//! `canonicalize` rewrites a format string into a normalised one that
+//! parses to exactly the same thing: no spacing inside placeholders except
+//! around the pipes of filters, options sorted by name, and escapes only
+//! where they're needed. Trim markers are applied, comments are kept. This
+//! is meant for tools that tidy up format strings kept in config files.
+//! ```
+//! use pfmt::pretty::canonicalize;
+//! use pfmt::syntax::Syntax;
+//!
+//! let s = canonicalize("{ a :x: width=r5:prec=2  |  upper } \\, {b{1}}", &Syntax::default());
+//! assert_eq!(s.unwrap(), "{a:x:prec=2:width=r5 | upper} , {b{1}}");
+//! ```
+
+use std::collections::HashMap;
+
+use parse::{
+    parse, FilterCall, Piece, ARG_SEPARATOR, CLOSING_PAREN, COMMENT, EXPRESSION, INCLUDE,
+    OPENING_PAREN, PIPE, RAW, TRIM,
+};
+use syntax::Syntax;
+use FormattingError;
+
+/// Parses a format string written in the given syntax, and writes it back in
+/// the canonical form. Fails if the format string doesn't parse.
+///
+/// Expressions are written as they were, since their source is a part of
+/// the parsed result. With a closing delimiter longer than one character, a
+/// name or a flag ending with the delimiter's first character can't be
+/// written right in front of the delimiter, so the result won't parse back
+/// the same.
+pub fn canonicalize(input: &str, syntax: &Syntax) -> Result<String, FormattingError> {
+    let pieces = parse(input, syntax)?;
+    let mut printer = Printer {
+        syntax,
+        out: String::new(),
+    };
+    let mut after_literal = false;
+    for piece in pieces.iter() {
+        match piece {
+            Piece::Literal(s) => {
+                printer.top_level_literal(s, after_literal);
+                after_literal = true;
+            }
+            piece => {
+                printer.piece(piece);
+                after_literal = false;
+            }
+        }
+    }
+    Ok(printer.out)
+}
+
+struct Printer<'s> {
+    syntax: &'s Syntax,
+    out: String,
+}
+
+impl<'s> Printer<'s> {
+    /// Writes a literal outside of placeholders. Adjacent literals (and empty
+    /// ones) only come from separators or raw blocks, so they're written
+    /// back as such.
+    fn top_level_literal(&mut self, s: &str, after_literal: bool) {
+        if !self.syntax.literal_separators {
+            if after_literal || s.is_empty() {
+                self.out.push(self.syntax.separator);
+            }
+            self.escaped(s, &[self.syntax.separator]);
+        } else if (after_literal || s.is_empty()) && self.fits_raw_block(s) {
+            self.raw_block(s);
+        } else {
+            self.escaped(s, &[]);
+        }
+    }
+
+    /// Writes a piece inside of a placeholder, or a top-level one that is not
+    /// a literal.
+    fn piece(&mut self, piece: &Piece) {
+        let syntax = self.syntax;
+        match piece {
+            Piece::Literal(s) => self.escaped(s, &[syntax.separator, PIPE, TRIM]),
+            Piece::Placeholder(name, args, flags, opts, filters) => {
+                self.out.push_str(&syntax.open);
+                for (i, segment) in name.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(syntax.path_separator);
+                    } else if segment.starts_with(&[INCLUDE, COMMENT, RAW, EXPRESSION][..]) {
+                        self.out.push(syntax.escape);
+                    }
+                    self.escaped(segment, &[syntax.separator, syntax.path_separator, PIPE, TRIM]);
+                }
+                self.arguments(args);
+                self.tail(flags, opts, filters);
+                self.out.push_str(&syntax.close);
+            }
+            Piece::Expression(expression, flags, opts, filters) => {
+                let source = &expression.source;
+                self.out.push_str(&syntax.open);
+                self.out.push(EXPRESSION);
+                self.out.push_str(source);
+                // Keep the expression from escaping or trimming the closing
+                // delimiter.
+                if source.ends_with(&[TRIM, syntax.escape][..]) {
+                    self.out.push(' ');
+                }
+                self.tail(flags, opts, filters);
+                self.out.push_str(&syntax.close);
+            }
+            Piece::Include(name) => {
+                self.out.push_str(&syntax.open);
+                self.out.push(INCLUDE);
+                self.out.push_str(name);
+                if name.ends_with(|ch| ch == TRIM || syntax.close.starts_with(ch)) {
+                    // Otherwise it could be read as a trim marker, or as a
+                    // part of the closing delimiter.
+                    self.out.push(' ');
+                }
+                self.out.push_str(&syntax.close);
+            }
+            Piece::Comment(text) => {
+                self.out.push_str(&syntax.open);
+                self.out.push(COMMENT);
+                self.out.push_str(text);
+                self.out.push(COMMENT);
+                self.out.push_str(&syntax.close);
+            }
+        }
+    }
+
+    fn arguments(&mut self, args: &[Piece]) {
+        if args.is_empty() {
+            return;
+        }
+        self.out.push_str(&self.syntax.open);
+        if args.len() == 1 && args[0] == Piece::Literal(String::new()) {
+            // An empty argument list would be read as no arguments at all.
+            self.raw_block("");
+        } else {
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(self.syntax.separator);
+                }
+                self.piece(arg);
+            }
+        }
+        self.out.push_str(&self.syntax.close);
+    }
+
+    /// Writes flags, options and filters, whichever are there.
+    fn tail(&mut self, flags: &[char], opts: &HashMap<String, Piece>, filters: &[FilterCall]) {
+        let syntax = self.syntax;
+        if !flags.is_empty() || !opts.is_empty() {
+            self.out.push(syntax.separator);
+            let flags: String = flags.iter().collect();
+            self.escaped(&flags, &[syntax.separator, PIPE, TRIM]);
+        }
+        let mut keys: Vec<&String> = opts.keys().collect();
+        keys.sort();
+        for key in keys {
+            self.out.push(syntax.separator);
+            self.escaped(key, &[syntax.separator, syntax.assign, PIPE, TRIM]);
+            let value = &opts[key];
+            if *value != Piece::Literal(String::new()) {
+                self.out.push(syntax.assign);
+                self.piece(value);
+            }
+        }
+        if !filters.is_empty() && (!flags.is_empty() || !opts.is_empty()) {
+            // Whitespace in front of the pipe would be trimmed off.
+            if let Some(ch) = self.out.pop() {
+                if ch.is_whitespace() {
+                    self.out.push(syntax.escape);
+                }
+                self.out.push(ch);
+            }
+        }
+        let filter_specials = [PIPE, OPENING_PAREN, CLOSING_PAREN, ARG_SEPARATOR, TRIM];
+        for filter in filters {
+            self.out.push(' ');
+            self.out.push(PIPE);
+            self.out.push(' ');
+            self.escaped(&filter.name, &filter_specials);
+            if !filter.args.is_empty() {
+                self.out.push(OPENING_PAREN);
+                for (i, arg) in filter.args.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(ARG_SEPARATOR);
+                        self.out.push(' ');
+                    }
+                    self.escaped(arg, &filter_specials);
+                }
+                self.out.push(CLOSING_PAREN);
+            }
+        }
+    }
+
+    /// Writes the text so that it's read back as is, escaping the delimiters,
+    /// the escape character and the given special characters.
+    fn escaped(&mut self, s: &str, specials: &[char]) {
+        let syntax = self.syntax;
+        let mut rest = s;
+        while let Some(ch) = rest.chars().next() {
+            let len = if rest.starts_with(&syntax.open[..]) {
+                syntax.open.len()
+            } else if rest.starts_with(&syntax.close[..]) {
+                syntax.close.len()
+            } else {
+                ch.len_utf8()
+            };
+            // A lone first character of a delimiter is escaped too, so that
+            // it can't form one with whatever follows.
+            if ch == syntax.escape
+                || specials.contains(&ch)
+                || syntax.open.starts_with(ch)
+                || syntax.close.starts_with(ch)
+            {
+                self.out.push(syntax.escape);
+            }
+            self.out.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+
+    fn fits_raw_block(&self, s: &str) -> bool {
+        let end = format!("{}{}", RAW, self.syntax.close);
+        let trimmed_end = format!("{}{}{}", RAW, TRIM, self.syntax.close);
+        !s.contains(&end[..]) && !s.contains(&trimmed_end[..])
+    }
+
+    fn raw_block(&mut self, s: &str) {
+        self.out.push_str(&self.syntax.open);
+        self.out.push(RAW);
+        self.out.push_str(s);
+        self.out.push(RAW);
+        self.out.push_str(&self.syntax.close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name canonical_form;
+        use galvanic_assert::matchers::*;
+
+        use parse::parse;
+        use pretty::canonicalize;
+        use syntax::Syntax;
+
+        fn round_trip(s: &str, syntax: &Syntax) {
+            let canonical = canonicalize(s, syntax).expect("Failed to canonicalize");
+            assert_that!(&parse(&canonical, syntax), eq(parse(s, syntax)));
+            assert_that!(&canonicalize(&canonical, syntax), eq(Ok(canonical.clone())));
+        }
+
+        test spacing_and_order() {
+            let s = canonicalize("{a.b {x:{y}}:xy: z=1: k={v} |  upper|wrap( [, ] )}",
+                                 &Syntax::default());
+            assert_that!(&s, eq(Ok("{a.b{x:{y}}:xy:k={v}:z=1 | upper | wrap([, ])}".to_string())));
+        }
+
+        test escaping() {
+            let s = canonicalize("a\\:b\\{\\}\\\\\\c:{\\#a\\:\\|b:\\~}{> t }{# c #}{!:{}!}",
+                                 &Syntax::default());
+            assert_that!(&s, eq(Ok("a\\:b\\{\\}\\\\c:{\\#a\\:\\|b:\\~}{>t}{# c #}\\:\\{\\}"
+                .to_string())));
+        }
+
+        test round_trips() {
+            let syntax = Syntax::default();
+            for s in &["", "a:b", "a::b", ":", "{a}:", "{f{:b}}", "{f{:}}", "{f{{!!}}}",
+                       "{f{{a}:{b}:}}", "{a::k:o=}", "{=a * 2 ::width=r4 | upper}",
+                       "{a:x | f() | g( , )}", "{~a ~}  {b}\n", "{a\\.b.c}", "{f{{g{x}}:y}}",
+                       "{a~}", "{a-b}", "{\\=a}", "{\\!a}", "{\\>a}", "{a::k=x\\  | upper}",
+                       "{a:x\\  | lower}"] {
+                round_trip(s, &syntax);
+            }
+        }
+
+        test custom_syntax() {
+            let syntax = Syntax::default().delimiters("<<", ">>").unwrap().escape('^').unwrap();
+            for s in &["<a<<x>>", "<<a<<b:^>>:<<c>>>>>>", "^<<{}:<<!!>>"] {
+                round_trip(s, &syntax);
+            }
+            let syntax = Syntax::default().literal_separators(true);
+            for s in &["a:b", "{!a!}{!!}{!b!}c", "{a}:{!:!}"] {
+                round_trip(s, &syntax);
+            }
+            let s = canonicalize("a:b{!c!}", &syntax);
+            assert_that!(&s, eq(Ok("a:b{!c!}".to_string())));
+        }
+
+    }
+}
@@ -0,0 +1,252 @@
+//! Configuration of the characters that make up the format string syntax.
+//!
+//! By default a placeholder looks like `{name.sub{arg}:flags:opt=value}`, and
+//! any of the special characters can be escaped with a backslash. When the
+//! output itself is full of these characters (JSON, LaTeX, Windows paths),
+//! escaping them all gets tedious, so they can be replaced. The placeholder
+//! delimiters can be longer than one character; argument lists use the same
+//! delimiters as placeholders. Pass the syntax to `FormatTable::format_with`
+//! as a part of a `Context`.
+//! ```
+//! use std::collections::HashMap;
+//! use pfmt::{Context, Fmt, FormatTable};
+//! use pfmt::syntax::Syntax;
+//!
+//! let name = "C:\\Users";
+//! let mut table: HashMap<&str, &Fmt> = HashMap::new();
+//! table.insert("dir", &name);
+//! let context = Context {
+//!     syntax: Syntax::default().delimiters("{{", "}}").unwrap().escape('^').unwrap(),
+//!     ..Context::default()
+//! };
+//! let s = table.format_with("{\"dir\"^: \"{{dir}}\\Public\"}", &context).unwrap();
+//! assert_eq!(s, "{\"dir\": \"C:\\Users\\Public\"}");
+//! ```
+//! Note that the colon still has to be escaped outside of placeholders,
+//! unless `literal_separators` is turned on.
+//!
+//! Since the delimiters and the special characters may well come from a
+//! configuration file, the syntax is checked as it's set up: each setter
+//! returns a `SyntaxError` if its change would leave two of them impossible
+//! to tell apart, or if it sets one of the special characters to one that
+//! isn't configurable. So to swap characters around, set the one that frees
+//! a character first.
+
+use parse::{COMMENT, EXPRESSION, INCLUDE, PIPE, RAW, TRIM};
+
+/// The characters with a fixed meaning, which the configurable ones can't
+/// be set to.
+const RESERVED: [char; 6] = [PIPE, TRIM, INCLUDE, COMMENT, RAW, EXPRESSION];
+
+/// The delimiters and special characters of format strings. The default is
+/// the usual `{`, `}`, `:`, `.`, `=` and `\`. Filters (`|`, `(`, `)` and
+/// `,`), includes (`>`), comments (`#`), raw blocks (`!`) and trim markers
+/// (`~`) are not configurable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syntax {
+    pub(crate) open: String,
+    pub(crate) close: String,
+    pub(crate) separator: char,
+    pub(crate) path_separator: char,
+    pub(crate) assign: char,
+    pub(crate) escape: char,
+    pub(crate) literal_separators: bool,
+}
+
+impl Default for Syntax {
+    fn default() -> Self {
+        Syntax {
+            open: "{".to_string(),
+            close: "}".to_string(),
+            separator: ':',
+            path_separator: '.',
+            assign: '=',
+            escape: '\\',
+            literal_separators: false,
+        }
+    }
+}
+
+impl Syntax {
+    /// Sets the strings that open and close placeholders and argument lists.
+    ///
+    /// Fails if either of them is empty, if one of them starts with the
+    /// other, or if either contains one of the special characters. The
+    /// characters with a fixed meaning are allowed in the delimiters, as
+    /// those are always read as a whole: `<<` and `>>` work fine.
+    pub fn delimiters(mut self, open: &str, close: &str) -> Result<Self, SyntaxError> {
+        self.open = open.to_string();
+        self.close = close.to_string();
+        self.check()
+    }
+
+    /// Sets the character separating the name, the flags and the options of
+    /// a placeholder, as well as arguments.
+    ///
+    /// Fails if it's already used by the syntax otherwise, or if it has a
+    /// fixed meaning (see `SyntaxError::ReservedCharacter`).
+    pub fn separator(mut self, separator: char) -> Result<Self, SyntaxError> {
+        self.separator = separator;
+        self.check()
+    }
+
+    /// Sets the character separating the segments of a name.
+    ///
+    /// Fails if it's already used by the syntax otherwise, or if it has a
+    /// fixed meaning (see `SyntaxError::ReservedCharacter`).
+    pub fn path_separator(mut self, path_separator: char) -> Result<Self, SyntaxError> {
+        self.path_separator = path_separator;
+        self.check()
+    }
+
+    /// Sets the character between an option's name and its value.
+    ///
+    /// Fails if it's already used by the syntax otherwise, or if it has a
+    /// fixed meaning (see `SyntaxError::ReservedCharacter`).
+    pub fn assign(mut self, assign: char) -> Result<Self, SyntaxError> {
+        self.assign = assign;
+        self.check()
+    }
+
+    /// Sets the escape character.
+    ///
+    /// Fails if it's already used by the syntax otherwise, or if it has a
+    /// fixed meaning (see `SyntaxError::ReservedCharacter`).
+    pub fn escape(mut self, escape: char) -> Result<Self, SyntaxError> {
+        self.escape = escape;
+        self.check()
+    }
+
+    /// Sets whether a separator outside of placeholders is taken literally.
+    /// By default it separates literals, and is dropped from the output, so
+    /// `12:30` has to be written as `12\:30`. With this turned on, the
+    /// separator only has a meaning inside placeholders.
+    pub fn literal_separators(mut self, literal_separators: bool) -> Self {
+        self.literal_separators = literal_separators;
+        self
+    }
+
+    /// Makes sure the delimiters and the special characters can be told
+    /// apart from each other.
+    fn check(self) -> Result<Self, SyntaxError> {
+        if self.open.is_empty() || self.close.is_empty() {
+            return Err(SyntaxError::EmptyDelimiter);
+        }
+        if self.open.starts_with(&self.close[..]) || self.close.starts_with(&self.open[..]) {
+            return Err(SyntaxError::AmbiguousDelimiters(
+                self.open.clone(),
+                self.close.clone(),
+            ));
+        }
+        for &ch in &[self.separator, self.path_separator, self.escape] {
+            if RESERVED.contains(&ch) {
+                return Err(SyntaxError::ReservedCharacter(ch));
+            }
+        }
+        // The expression marker is only special at the start of a
+        // placeholder, where there's no option to assign to, so it's the
+        // default assignment character as well.
+        if self.assign != EXPRESSION && RESERVED.contains(&self.assign) {
+            return Err(SyntaxError::ReservedCharacter(self.assign));
+        }
+        let chars = [self.separator, self.path_separator, self.assign, self.escape];
+        for (i, &ch) in chars.iter().enumerate() {
+            if chars[..i].contains(&ch) {
+                return Err(SyntaxError::DuplicateCharacter(ch));
+            }
+            for delimiter in &[&self.open, &self.close] {
+                if delimiter.contains(ch) {
+                    return Err(SyntaxError::CharacterInDelimiter(ch, delimiter.to_string()));
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// The reasons a `Syntax` can't be set up the way it's asked to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxError {
+    /// One of the delimiters is empty.
+    EmptyDelimiter,
+    /// One of the delimiters starts with the other one (or is equal to it),
+    /// so they can't be told apart. Contains the opening and the closing
+    /// delimiter.
+    AmbiguousDelimiters(String, String),
+    /// Two of the special characters are the same. Contains the character.
+    DuplicateCharacter(char),
+    /// One of the special characters is a part of a delimiter. Contains the
+    /// character and the delimiter.
+    CharacterInDelimiter(char, String),
+    /// One of the special characters is set to a character with a fixed
+    /// meaning: `|`, `~`, `>`, `#`, `!` or `=` (the latter is only allowed
+    /// for the assignment). Contains the character.
+    ReservedCharacter(char),
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name validation;
+        use galvanic_assert::matchers::*;
+
+        use syntax::{Syntax, SyntaxError};
+
+        test valid() {
+            let syntax = Syntax::default()
+                .delimiters("<%", "%>")
+                .and_then(|s| s.separator(';'))
+                .and_then(|s| s.escape(':'))
+                .and_then(|s| s.path_separator('/'))
+                .and_then(|s| s.assign('.'));
+            assert_that!(&syntax.is_ok(), eq(true));
+        }
+
+        test delimiters() {
+            let res = Syntax::default().delimiters("", "}");
+            assert_that!(&res, eq(Err(SyntaxError::EmptyDelimiter)));
+            let res = Syntax::default().delimiters("%", "%");
+            assert_that!(&res, eq(Err(SyntaxError::AmbiguousDelimiters(
+                "%".to_string(), "%".to_string()))));
+            let res = Syntax::default().delimiters("<", "<<");
+            assert_that!(&res, eq(Err(SyntaxError::AmbiguousDelimiters(
+                "<".to_string(), "<<".to_string()))));
+            let res = Syntax::default().delimiters("\\{", "}");
+            assert_that!(&res, eq(Err(SyntaxError::CharacterInDelimiter(
+                '\\', "\\{".to_string()))));
+            let res = Syntax::default().delimiters("{:", ":}");
+            assert_that!(&res, eq(Err(SyntaxError::CharacterInDelimiter(
+                ':', "{:".to_string()))));
+        }
+
+        test special_characters() {
+            let res = Syntax::default().escape(':');
+            assert_that!(&res, eq(Err(SyntaxError::DuplicateCharacter(':'))));
+            let res = Syntax::default().assign('.');
+            assert_that!(&res, eq(Err(SyntaxError::DuplicateCharacter('.'))));
+            let res = Syntax::default().separator('}');
+            assert_that!(&res, eq(Err(SyntaxError::CharacterInDelimiter(
+                '}', "}".to_string()))));
+            let res = Syntax::default().delimiters("[", "]").and_then(|s| s.path_separator('{'));
+            assert_that!(&res.is_ok(), eq(true));
+        }
+
+        test reserved_characters() {
+            let res = Syntax::default().separator('|');
+            assert_that!(&res, eq(Err(SyntaxError::ReservedCharacter('|'))));
+            let res = Syntax::default().escape('~');
+            assert_that!(&res, eq(Err(SyntaxError::ReservedCharacter('~'))));
+            let res = Syntax::default().path_separator('>');
+            assert_that!(&res, eq(Err(SyntaxError::ReservedCharacter('>'))));
+            let res = Syntax::default().assign('#');
+            assert_that!(&res, eq(Err(SyntaxError::ReservedCharacter('#'))));
+            let res = Syntax::default().assign(';').and_then(|s| s.separator('='));
+            assert_that!(&res, eq(Err(SyntaxError::ReservedCharacter('='))));
+            let res = Syntax::default().assign('!');
+            assert_that!(&res, eq(Err(SyntaxError::ReservedCharacter('!'))));
+            let res = Syntax::default().assign(';').and_then(|s| s.assign('='));
+            assert_that!(&res.is_ok(), eq(true));
+        }
+
+    }
+}
@@ -0,0 +1,1114 @@
+//! Ready-made format tables and table combinators.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::rc::Rc;
+
+use value::{self, Value};
+use {util, BoxOrRef, Fmt, FormatTable, FormattingError, SingleFmtError};
+
+/* ---------- environment ---------- */
+
+/// A format table that resolves names to environment variables. The values
+/// are formatted as `String`s, so all the common options work on them.
+///
+/// By default, the variables are taken from the environment of the current
+/// process at the time of formatting, but a table can also be created from
+/// a map, which is handy in tests. A table can be restricted to only the
+/// variables whose names start with a given prefix, so that a user-editable
+/// format string cannot peek at everything in the environment.
+/// ```
+/// use std::collections::HashMap;
+/// use pfmt::FormatTable;
+/// use pfmt::tables::EnvTable;
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("HOME".to_string(), "/home/user".to_string());
+/// vars.insert("APP_ENV".to_string(), "prod".to_string());
+/// let table = EnvTable::from_map(vars);
+/// let s = table.format("{HOME}/logs/{APP_ENV::width=l6}|").unwrap();
+/// assert_eq!(s, "/home/user/logs/prod  |");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EnvTable {
+    prefix: Option<String>,
+    vars: Option<HashMap<String, String>>,
+}
+
+impl EnvTable {
+    /// Creates a table looking up the environment of the current process.
+    pub fn new() -> Self {
+        EnvTable {
+            prefix: None,
+            vars: None,
+        }
+    }
+
+    /// Creates a table looking up variables in the given map instead of the
+    /// actual environment.
+    pub fn from_map(vars: HashMap<String, String>) -> Self {
+        EnvTable {
+            prefix: None,
+            vars: Some(vars),
+        }
+    }
+
+    /// Only allows looking up the variables whose names start with the
+    /// given prefix. The placeholders still use the full names of the
+    /// variables.
+    pub fn set_prefix(&mut self, prefix: &str) {
+        self.prefix = Some(prefix.to_string());
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        if let Some(prefix) = &self.prefix {
+            if !name.starts_with(prefix.as_str()) {
+                return None;
+            }
+        }
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => env::var(name).ok(),
+        }
+    }
+}
+
+impl FormatTable for EnvTable {
+    fn get_fmt<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        self.lookup(name)
+            .map(|value| BoxOrRef::Boxed(Box::new(value) as Box<dyn Fmt>))
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        let names: Vec<String> = match &self.vars {
+            Some(vars) => vars.keys().cloned().collect(),
+            None => unicode_names(env::vars_os().map(|(name, _)| name)),
+        };
+        let prefix = self.prefix.as_ref().map_or("", |prefix| prefix.as_str());
+        Some(names.into_iter().filter(|name| name.starts_with(prefix)).collect())
+    }
+}
+
+/// Keeps the variable names that are valid Unicode, skipping the others
+/// (which no placeholder can name anyway) rather than panicking on them
+/// like `env::vars` does.
+fn unicode_names<I: Iterator<Item = OsString>>(names: I) -> Vec<String> {
+    names.filter_map(|name| name.into_string().ok()).collect()
+}
+
+/* ---------- scopes ---------- */
+
+/// A stack of format tables, which can be pushed and popped at runtime. A
+/// name is looked up in the innermost (most recently pushed) table first, and
+/// then outwards, so inner tables can shadow `Fmt`s from the outer ones. The
+/// tables can be either owned by the stack or borrowed.
+///
+/// This generalizes the `impl`s of `FormatTable` for tuples: `(a, b)` looks
+/// names up the same way as a `Scopes` with `b` pushed first and `a` second.
+/// ```
+/// use std::collections::HashMap;
+/// use pfmt::{Fmt, FormatTable};
+/// use pfmt::tables::Scopes;
+///
+/// let mut globals: HashMap<&str, Box<Fmt>> = HashMap::new();
+/// globals.insert("user", Box::new("root"));
+/// globals.insert("i", Box::new(0));
+/// let mut scopes = Scopes::new();
+/// scopes.push_ref(&globals);
+/// let mut lines = Vec::new();
+/// for i in 1..3 {
+///     let mut locals: HashMap<&str, Box<Fmt>> = HashMap::new();
+///     locals.insert("i", Box::new(i));
+///     scopes.push(locals);
+///     lines.push(scopes.format("{user}\\: {i}").unwrap());
+///     scopes.pop();
+/// }
+/// lines.push(scopes.format("{user}\\: {i}").unwrap());
+/// assert_eq!(lines, vec!["root: 1", "root: 2", "root: 0"]);
+/// ```
+#[derive(Default)]
+pub struct Scopes<'a> {
+    layers: Vec<BoxOrRef<'a, dyn FormatTable + 'a>>,
+}
+
+impl<'a> Scopes<'a> {
+    pub fn new() -> Self {
+        Scopes { layers: Vec::new() }
+    }
+
+    /// Pushes a table, which the stack takes ownership of, as the new
+    /// innermost scope.
+    pub fn push<T: FormatTable + 'a>(&mut self, table: T) {
+        self.layers.push(BoxOrRef::Boxed(Box::new(table)));
+    }
+
+    /// Pushes a borrowed table as the new innermost scope.
+    pub fn push_ref(&mut self, table: &'a dyn FormatTable) {
+        self.layers.push(BoxOrRef::Ref(table));
+    }
+
+    /// Removes the innermost scope and returns it, or `None` if the stack is
+    /// empty.
+    pub fn pop(&mut self) -> Option<BoxOrRef<'a, dyn FormatTable + 'a>> {
+        self.layers.pop()
+    }
+
+    /// Returns the number of scopes on the stack.
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+}
+
+impl<'a> FormatTable for Scopes<'a> {
+    fn get_fmt<'b>(&'b self, name: &str) -> Option<BoxOrRef<'b, dyn Fmt>> {
+        self.layers.iter().rev().filter_map(|table| table.get_fmt(name)).next()
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        ::merge_names(self.layers.iter().map(|table| table.names()))
+    }
+}
+
+/* ---------- namespaces ---------- */
+
+/// A format table used as a `Fmt`, which gives access to the `Fmt`s of the
+/// table with the dot syntax. If `db` is a `Namespace`, `{db.host}` looks up
+/// `host` in the wrapped table, and any further segments of the name are
+/// passed to the found `Fmt`, so `{db.primary.port}` works if `primary`
+/// supports sub-`Fmt`s. This way, a table put into another table looks no
+/// different from a nested `Fmt` in format strings.
+/// ```
+/// use std::collections::HashMap;
+/// use pfmt::{Fmt, FormatTable};
+/// use pfmt::tables::Namespace;
+///
+/// let mut db: HashMap<&str, Box<Fmt>> = HashMap::new();
+/// db.insert("host", Box::new("localhost"));
+/// let db = Namespace(db);
+/// let id = 1;
+/// let mut table: HashMap<&str, &Fmt> = HashMap::new();
+/// table.insert("db", &db);
+/// table.insert("id", &id);
+/// let s = table.format("{db.host}, {id}").unwrap();
+/// assert_eq!(s, "localhost, 1");
+/// ```
+pub struct Namespace<T>(pub T);
+
+impl<T: FormatTable> Namespace<T> {
+    fn sub_fmt<'a>(
+        &'a self,
+        full_name: &[String],
+        name: &[String],
+    ) -> Result<BoxOrRef<'a, dyn Fmt>, SingleFmtError> {
+        if name.is_empty() {
+            return Err(SingleFmtError::NamespaceOnlyFmt(util::join_name(full_name)));
+        }
+        self.0
+            .get_fmt(&name[0])
+            .ok_or_else(|| SingleFmtError::UnknownSubfmt(util::join_name(full_name)))
+    }
+}
+
+impl<T: FormatTable> Fmt for Namespace<T> {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        self.sub_fmt(full_name, name)?
+            .format(full_name, &name[1..], args, flags, options)
+    }
+
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        self.sub_fmt(full_name, name)?
+            .format_values(full_name, &name[1..], args, flags, options)
+    }
+
+    fn sub_names(&self, name: &[String]) -> Option<Vec<String>> {
+        match name.split_first() {
+            None => self.0.names(),
+            Some((first, rest)) => self.0.get_fmt(first)?.sub_names(rest),
+        }
+    }
+
+    fn sub_known_flags(&self, name: &[String]) -> Option<&'static [char]> {
+        match name.split_first() {
+            None => self.known_flags(),
+            Some((first, rest)) => self.0.get_fmt(first)?.sub_known_flags(rest),
+        }
+    }
+
+    fn sub_known_options(&self, name: &[String]) -> Option<&'static [&'static str]> {
+        match name.split_first() {
+            None => self.known_options(),
+            Some((first, rest)) => self.0.get_fmt(first)?.sub_known_options(rest),
+        }
+    }
+}
+
+/// A format table mounted under a prefix: a table containing a single
+/// `Namespace`. Use tuples to combine several mounted tables, so that names
+/// from different subsystems don't collide.
+/// ```
+/// use std::collections::HashMap;
+/// use pfmt::{Fmt, FormatTable};
+/// use pfmt::tables::Mounted;
+///
+/// let mut db: HashMap<&str, Box<Fmt>> = HashMap::new();
+/// db.insert("host", Box::new("localhost"));
+/// db.insert("id", Box::new(1));
+/// let mut app: HashMap<&str, Box<Fmt>> = HashMap::new();
+/// app.insert("id", Box::new(2));
+/// let table = (Mounted::new("db", db), Mounted::new("app", app));
+/// let s = table.format("{db.host}, {db.id}, {app.id}").unwrap();
+/// assert_eq!(s, "localhost, 1, 2");
+/// ```
+pub struct Mounted<T> {
+    prefix: String,
+    namespace: Namespace<T>,
+}
+
+impl<T: FormatTable> Mounted<T> {
+    pub fn new(prefix: &str, table: T) -> Self {
+        Mounted {
+            prefix: prefix.to_string(),
+            namespace: Namespace(table),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn table(&self) -> &T {
+        &self.namespace.0
+    }
+}
+
+impl<T: FormatTable> FormatTable for Mounted<T> {
+    fn get_fmt<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        if name == self.prefix {
+            Some(BoxOrRef::Ref(&self.namespace))
+        } else {
+            None
+        }
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(vec![self.prefix.clone()])
+    }
+}
+
+/* ---------- aliases ---------- */
+
+type AliasReporter = dyn Fn(&str, &str);
+
+/// A format table wrapper that maps alias names to canonical ones before
+/// looking them up in the wrapped table. Both aliases and canonical names
+/// may be dotted paths: with `old.host` aliased to `server.hostname`,
+/// `{old.host}` is formatted as `{server.hostname}` would be, and
+/// `{old.host.x}` as `{server.hostname.x}`. If several aliases match a name,
+/// the longest one wins. Names that don't match any alias are looked up
+/// as is.
+///
+/// A reporter can be set to be notified of every use of an alias, for
+/// instance to warn users about deprecated names.
+/// ```
+/// use std::cell::RefCell;
+/// use std::collections::HashMap;
+/// use std::rc::Rc;
+/// use pfmt::{Fmt, FormatTable};
+/// use pfmt::tables::Aliased;
+///
+/// let mut inner: HashMap<&str, Box<Fmt>> = HashMap::new();
+/// inner.insert("user_name", Box::new("alice"));
+/// let mut table = Aliased::new(inner);
+/// table.alias("login", "user_name");
+/// let hits = Rc::new(RefCell::new(Vec::new()));
+/// let reported = hits.clone();
+/// table.set_reporter(move |alias, canonical| {
+///     reported.borrow_mut().push(format!("{} -> {}", alias, canonical));
+/// });
+/// let s = table.format("{login}, {user_name}").unwrap();
+/// assert_eq!(s, "alice, alice");
+/// assert_eq!(*hits.borrow(), vec!["login -> user_name"]);
+/// ```
+pub struct Aliased<T> {
+    table: T,
+    aliases: HashMap<Vec<String>, Vec<String>>,
+    reporter: Option<Box<AliasReporter>>,
+}
+
+impl<T: FormatTable> Aliased<T> {
+    pub fn new(table: T) -> Self {
+        Aliased {
+            table,
+            aliases: HashMap::new(),
+            reporter: None,
+        }
+    }
+
+    /// Makes `alias` refer to `canonical`. Both are names with segments
+    /// separated by dots.
+    pub fn alias(&mut self, alias: &str, canonical: &str) {
+        self.aliases.insert(split_name(alias), split_name(canonical));
+    }
+
+    /// Sets a function to be called with the alias and the canonical name
+    /// (both dot-separated) whenever an alias is used.
+    pub fn set_reporter<F: Fn(&str, &str) + 'static>(&mut self, reporter: F) {
+        self.reporter = Some(Box::new(reporter));
+    }
+
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Finds the longest alias that is a prefix of the given path, returning
+    /// its length and the canonical name it stands for.
+    fn find_alias(&self, path: &[String]) -> Option<(usize, &Vec<String>)> {
+        (1..=path.len())
+            .rev()
+            .filter_map(|len| self.aliases.get(&path[..len]).map(|canonical| (len, canonical)))
+            .next()
+    }
+
+    /// Returns the path with its alias replaced by the canonical name, if it
+    /// starts with one. Doesn't report the alias, as this is also used to
+    /// answer queries about the name rather than to format it.
+    fn resolve(&self, path: &[String]) -> Option<Vec<String>> {
+        self.find_alias(path).map(|(len, canonical)| {
+            let mut res = canonical.clone();
+            res.extend_from_slice(&path[len..]);
+            res
+        })
+    }
+
+    /// Tells the reporter about the alias the path starts with, if any.
+    fn report(&self, path: &[String]) {
+        if let (Some(reporter), Some((len, canonical))) = (&self.reporter, self.find_alias(path)) {
+            reporter(&util::join_name(&path[..len]), &util::join_name(canonical));
+        }
+    }
+}
+
+impl<T: FormatTable> FormatTable for Aliased<T> {
+    fn get_fmt<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        if self.aliases.keys().any(|alias| alias[0] == name) {
+            Some(BoxOrRef::Boxed(Box::new(AliasResolver {
+                aliased: self,
+                first: name.to_string(),
+            })))
+        } else {
+            self.table.get_fmt(name)
+        }
+    }
+
+    /// The names of the wrapped table, along with the first segments of the
+    /// aliases.
+    fn names(&self) -> Option<Vec<String>> {
+        let aliases: Vec<String> = self.aliases.keys().map(|alias| alias[0].clone()).collect();
+        let names = self.table.names()?;
+        ::merge_names(vec![Some(names), Some(aliases)])
+    }
+}
+
+/// The `Fmt` standing for a name that may be an alias. Which alias it is
+/// (if any) depends on the rest of the name, which is only known when it's
+/// formatted.
+struct AliasResolver<'a, T: 'a> {
+    aliased: &'a Aliased<T>,
+    first: String,
+}
+
+impl<'a, T: FormatTable> AliasResolver<'a, T> {
+    /// The whole path of the name, starting with the first segment.
+    fn path(&self, name: &[String]) -> Vec<String> {
+        let mut path = Vec::with_capacity(name.len() + 1);
+        path.push(self.first.clone());
+        path.extend_from_slice(name);
+        path
+    }
+
+    /// Resolves the name, returning the `Fmt` it starts with and the
+    /// resolved path.
+    fn resolve_fmt(
+        &self,
+        full_name: &[String],
+        name: &[String],
+    ) -> Result<(BoxOrRef<'a, dyn Fmt>, Vec<String>), SingleFmtError> {
+        let path = self.path(name);
+        let path = self.aliased.resolve(&path).unwrap_or(path);
+        match self.aliased.table.get_fmt(&path[0]) {
+            Some(fmt) => Ok((fmt, path)),
+            None => Err(SingleFmtError::UnknownSubfmt(util::join_name(full_name))),
+        }
+    }
+}
+
+impl<'a, T: FormatTable> Fmt for AliasResolver<'a, T> {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        self.aliased.report(&self.path(name));
+        let (fmt, path) = self.resolve_fmt(full_name, name)?;
+        fmt.format(full_name, &path[1..], args, flags, options)
+    }
+
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        self.aliased.report(&self.path(name));
+        let (fmt, path) = self.resolve_fmt(full_name, name)?;
+        fmt.format_values(full_name, &path[1..], args, flags, options)
+    }
+
+    fn sub_names(&self, name: &[String]) -> Option<Vec<String>> {
+        let (fmt, path) = self.resolve_fmt(&[], name).ok()?;
+        fmt.sub_names(&path[1..])
+    }
+
+    fn sub_known_flags(&self, name: &[String]) -> Option<&'static [char]> {
+        let (fmt, path) = self.resolve_fmt(&[], name).ok()?;
+        fmt.sub_known_flags(&path[1..])
+    }
+
+    fn sub_known_options(&self, name: &[String]) -> Option<&'static [&'static str]> {
+        let (fmt, path) = self.resolve_fmt(&[], name).ok()?;
+        fmt.sub_known_options(&path[1..])
+    }
+}
+
+fn split_name(name: &str) -> Vec<String> {
+    name.split('.').map(|segment| segment.trim().to_string()).collect()
+}
+
+/* ---------- caching ---------- */
+
+/// A format table wrapper that remembers the `Fmt`s produced by the wrapped
+/// table, so that a `Fmt` that is expensive to create is only created once,
+/// however many times it's used. Optionally, it can also remember the output
+/// of each `Fmt` for each combination of sub-name, arguments, flags and
+/// options, for `Fmt`s that are expensive to format.
+///
+/// The cache lives as long as the `Cached` itself. To cache only for the
+/// duration of a single formatting call, wrap the table right before
+/// formatting: `Cached::new(&table).format(input)`. To keep the cached
+/// values around for longer, keep the `Cached`, and `clear` it when they
+/// become stale.
+/// ```
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+/// use pfmt::{Fmt, FormatTable};
+/// use pfmt::adapters::FnTable;
+/// use pfmt::tables::Cached;
+///
+/// let calls = Rc::new(Cell::new(0));
+/// let counter = calls.clone();
+/// let table = FnTable::new(move |name| {
+///     counter.set(counter.get() + 1);
+///     if name == "usage" {
+///         Some(Box::new(42) as Box<Fmt>)
+///     } else {
+///         None
+///     }
+/// });
+/// let s = Cached::new(&table).format("{usage}, {usage:x}, {usage:b}").unwrap();
+/// assert_eq!(s, "42, 2a, 101010");
+/// assert_eq!(calls.get(), 1);
+/// ```
+pub struct Cached<'t, T: 't + ?Sized> {
+    table: &'t T,
+    fmts: RefCell<HashMap<String, Option<SharedBox<'t>>>>,
+    outputs: Option<RefCell<HashMap<OutputKey, String>>>,
+}
+
+type SharedBox<'t> = Rc<BoxOrRef<'t, dyn Fmt + 't>>;
+
+/// Everything that can affect the output of a `Fmt`.
+#[derive(PartialEq, Eq, Hash)]
+struct OutputKey {
+    full_name: Vec<String>,
+    name: Vec<String>,
+    args: Vec<String>,
+    flags: Vec<char>,
+    options: Vec<(String, String)>,
+}
+
+impl OutputKey {
+    fn new(
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Self {
+        let mut sorted_options: Vec<(String, String)> =
+            options.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        sorted_options.sort();
+        OutputKey {
+            full_name: full_name.to_vec(),
+            name: name.to_vec(),
+            args: args.to_vec(),
+            flags: flags.to_vec(),
+            options: sorted_options,
+        }
+    }
+}
+
+impl<'t, T: FormatTable + ?Sized> Cached<'t, T> {
+    pub fn new(table: &'t T) -> Self {
+        Cached {
+            table,
+            fmts: RefCell::new(HashMap::new()),
+            outputs: None,
+        }
+    }
+
+    /// Enables or disables caching of the output of the `Fmt`s. Disabling it
+    /// drops everything cached so far.
+    ///
+    /// The outputs are remembered by the rendered arguments and options, so
+    /// with the cache on, all of them are evaluated, even for `Fmt`s that
+    /// would skip some. If any of them fails to render, the `Fmt` is called
+    /// as usual, and its output isn't cached.
+    pub fn set_cache_output(&mut self, enabled: bool) {
+        self.outputs = if enabled {
+            Some(RefCell::new(HashMap::new()))
+        } else {
+            None
+        };
+    }
+
+    /// Forgets all the cached `Fmt`s and outputs.
+    pub fn clear(&self) {
+        self.fmts.borrow_mut().clear();
+        if let Some(outputs) = &self.outputs {
+            outputs.borrow_mut().clear();
+        }
+    }
+}
+
+impl<'t, T: FormatTable + ?Sized> FormatTable for Cached<'t, T> {
+    fn get_fmt<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        let cached = self.fmts.borrow().get(name).cloned();
+        let fmt = match cached {
+            Some(fmt) => fmt,
+            None => {
+                let fmt = self.table.get_fmt(name).map(Rc::new);
+                self.fmts.borrow_mut().insert(name.to_string(), fmt.clone());
+                fmt
+            }
+        };
+        fmt.map(|fmt| {
+            BoxOrRef::Boxed(Box::new(SharedFmt {
+                fmt,
+                outputs: self.outputs.as_ref(),
+            }) as Box<dyn Fmt + 'a>)
+        })
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        self.table.names()
+    }
+}
+
+/// A `Fmt` from the cache of a `Cached`.
+struct SharedFmt<'a, 't: 'a> {
+    fmt: SharedBox<'t>,
+    outputs: Option<&'a RefCell<HashMap<OutputKey, String>>>,
+}
+
+impl<'a, 't> Fmt for SharedFmt<'a, 't> {
+    fn format(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[String],
+        flags: &[char],
+        options: &HashMap<String, String>,
+    ) -> Result<String, SingleFmtError> {
+        let outputs = match self.outputs {
+            Some(outputs) => outputs,
+            None => return self.fmt.format(full_name, name, args, flags, options),
+        };
+        let key = OutputKey::new(full_name, name, args, flags, options);
+        if let Some(output) = outputs.borrow().get(&key) {
+            return Ok(output.clone());
+        }
+        let output = self.fmt.format(full_name, name, args, flags, options)?;
+        outputs.borrow_mut().insert(key, output.clone());
+        Ok(output)
+    }
+
+    fn format_values(
+        &self,
+        full_name: &[String],
+        name: &[String],
+        args: &[Value],
+        flags: &[char],
+        options: &HashMap<String, Value>,
+    ) -> Result<String, FormattingError> {
+        let outputs = match self.outputs {
+            Some(outputs) => outputs,
+            None => return self.fmt.format_values(full_name, name, args, flags, options),
+        };
+        // The output cache is keyed by the rendered values. The `Fmt` might
+        // not need all of them though, so if some fail to render, it's left
+        // to the `Fmt` to decide, without the cache.
+        let key = match value::render_all(args, options) {
+            Ok((rendered_args, rendered_options)) => OutputKey::new(
+                full_name,
+                name,
+                &rendered_args,
+                flags,
+                &rendered_options,
+            ),
+            Err(_) => return self.fmt.format_values(full_name, name, args, flags, options),
+        };
+        if let Some(output) = outputs.borrow().get(&key) {
+            return Ok(output.clone());
+        }
+        // The values remember how they were rendered, so this doesn't
+        // evaluate them again.
+        let output = self.fmt.format_values(full_name, name, args, flags, options)?;
+        outputs.borrow_mut().insert(key, output.clone());
+        Ok(output)
+    }
+
+    fn known_flags(&self) -> Option<&'static [char]> {
+        self.fmt.known_flags()
+    }
+
+    fn known_options(&self) -> Option<&'static [&'static str]> {
+        self.fmt.known_options()
+    }
+
+    fn sub_names(&self, name: &[String]) -> Option<Vec<String>> {
+        self.fmt.sub_names(name)
+    }
+
+    fn sub_known_flags(&self, name: &[String]) -> Option<&'static [char]> {
+        self.fmt.sub_known_flags(name)
+    }
+
+    fn sub_known_options(&self, name: &[String]) -> Option<&'static [&'static str]> {
+        self.fmt.sub_known_options(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name env;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use tables::EnvTable;
+        use {FormatTable, FormattingError};
+
+        fn vars() -> HashMap<String, String> {
+            let mut vars = HashMap::new();
+            vars.insert("HOME".to_string(), "/home/user".to_string());
+            vars.insert("APP_NAME".to_string(), "pfmt".to_string());
+            vars
+        }
+
+        test from_map() {
+            let table = EnvTable::from_map(vars());
+            let s = table.format("{APP_NAME::truncate=r2}, {HOME}");
+            assert_that!(&s, eq(Ok("pf, /home/user".to_string())));
+        }
+
+        test missing() {
+            let table = EnvTable::from_map(vars());
+            let s = table.format("{USER}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("USER".to_string(), vec![]))));
+        }
+
+        test prefix() {
+            let mut table = EnvTable::from_map(vars());
+            table.set_prefix("APP_");
+            let s = table.format("{APP_NAME}");
+            assert_that!(&s, eq(Ok("pfmt".to_string())));
+            let s = table.format("{HOME}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("HOME".to_string(), vec![]))));
+        }
+
+        test names() {
+            let mut table = EnvTable::from_map(vars());
+            let mut names = table.names().unwrap();
+            names.sort();
+            assert_that!(&names, eq(vec!["APP_NAME".to_string(), "HOME".to_string()]));
+            table.set_prefix("APP_");
+            assert_that!(&table.names(), eq(Some(vec!["APP_NAME".to_string()])));
+        }
+
+    }
+
+    #[cfg(unix)]
+    test_suite! {
+        name env_os;
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+        use galvanic_assert::matchers::*;
+
+        use tables::unicode_names;
+
+        test non_unicode_names() {
+            let names = vec![OsString::from("HOME"), OsString::from_vec(vec![b'B', 0xff])];
+            assert_that!(&unicode_names(names.into_iter()), eq(vec!["HOME".to_string()]));
+        }
+
+    }
+
+    test_suite! {
+        name scopes;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use tables::Scopes;
+        use {Fmt, FormatTable, FormattingError};
+
+        test innermost_first() {
+            let outer: Vec<Box<dyn Fmt>> = vec![Box::new(1), Box::new(2)];
+            let inner: Vec<Box<dyn Fmt>> = vec![Box::new(10)];
+            let mut scopes = Scopes::new();
+            scopes.push(outer);
+            scopes.push(inner);
+            let s = scopes.format("{0}, {1}");
+            assert_that!(&s, eq(Ok("10, 2".to_string())));
+            scopes.pop();
+            let s = scopes.format("{0}, {1}");
+            assert_that!(&s, eq(Ok("1, 2".to_string())));
+        }
+
+        test same_as_tuples() {
+            let a: Vec<Box<dyn Fmt>> = vec![Box::new(1)];
+            let b: Vec<Box<dyn Fmt>> = vec![Box::new(10), Box::new(20)];
+            let mut scopes = Scopes::new();
+            scopes.push_ref(&b);
+            scopes.push_ref(&a);
+            let s1 = scopes.format("{0}, {1}");
+            let s2 = (&a, &b).format("{0}, {1}");
+            assert_that!(&s1, eq(s2));
+        }
+
+        test unbounded_depth() {
+            let mut scopes = Scopes::new();
+            for i in 0..20 {
+                let mut table: HashMap<String, Box<dyn Fmt>> = HashMap::new();
+                table.insert(format!("v{}", i), Box::new(i));
+                table.insert("last".to_string(), Box::new(i));
+                scopes.push(table);
+            }
+            assert_that!(&scopes.depth(), eq(20));
+            let s = scopes.format("{v0}, {v19}, {last}");
+            assert_that!(&s, eq(Ok("0, 19, 19".to_string())));
+        }
+
+        test empty() {
+            let mut scopes = Scopes::new();
+            assert_that!(&scopes.pop().is_none(), eq(true));
+            let s = scopes.format("{a}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("a".to_string(), vec![]))));
+        }
+
+    }
+
+    test_suite! {
+        name mounted;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use tables::{EnvTable, Mounted, Namespace};
+        use {Fmt, FormatTable, FormattingError};
+
+        fn env() -> EnvTable {
+            let mut vars = HashMap::new();
+            vars.insert("HOME".to_string(), "/root".to_string());
+            EnvTable::from_map(vars)
+        }
+
+        test lookup() {
+            let table = Mounted::new("env", env());
+            let s = table.format("{env.HOME::width=r6}");
+            assert_that!(&s, eq(Ok(" /root".to_string())));
+        }
+
+        test namespace_only() {
+            let table = Mounted::new("env", env());
+            let s = table.format("{env}");
+            assert_that!(&s, eq(Err(FormattingError::NamespaceOnlyFmt("env".to_string()))));
+        }
+
+        test unknown() {
+            let table = Mounted::new("env", env());
+            let s = table.format("{env.USER}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("env.USER".to_string(), vec![]))));
+            let s = table.format("{HOME}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("HOME".to_string(), vec![]))));
+        }
+
+        test nested_mounts() {
+            let table = Mounted::new("a", Mounted::new("b", env()));
+            let s = table.format("{a.b.HOME}");
+            assert_that!(&s, eq(Ok("/root".to_string())));
+        }
+
+        test alongside_fmts() {
+            let namespace = Namespace(env());
+            let i = 1;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("env", &namespace);
+            table.insert("i", &i);
+            let s = table.format("{env.HOME}, {i}");
+            assert_that!(&s, eq(Ok("/root, 1".to_string())));
+        }
+
+    }
+
+    test_suite! {
+        name aliased;
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+        use galvanic_assert::matchers::*;
+
+        use tables::{Aliased, Mounted, Namespace};
+        use {Context, Fmt, FormatTable, FormattingError};
+
+        fn server() -> HashMap<&'static str, Box<dyn Fmt>> {
+            let mut server: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            server.insert("hostname", Box::new("example.org"));
+            server.insert("port", Box::new(80));
+            server
+        }
+
+        test plain() {
+            let mut table = Aliased::new(server());
+            table.alias("host", "hostname");
+            let s = table.format("{host::width=r12}, {port}");
+            assert_that!(&s, eq(Ok(" example.org, 80".to_string())));
+        }
+
+        test dotted() {
+            let mut table = Aliased::new(Mounted::new("server", server()));
+            table.alias("old.host", "server.hostname");
+            table.alias("addr", "server");
+            let s = table.format("{old.host}, {addr.port}, {server.port}");
+            assert_that!(&s, eq(Ok("example.org, 80, 80".to_string())));
+        }
+
+        test strict() {
+            let mut table = Aliased::new(Mounted::new("server", server()));
+            table.alias("old.host", "server.hostname");
+            table.alias("addr", "server");
+            let context = Context { strict: true, ..Context::default() };
+            for input in ["{old.host:zz}", "{addr.port:zz}", "{server.port:zz}"].iter() {
+                let s = table.format_with(input, &context);
+                assert_that!(&s, eq(Err(FormattingError::UnknownFlag('z'))));
+            }
+            let s = table.format_with("{old.host::width=r12}", &context);
+            assert_that!(&s, eq(Ok(" example.org".to_string())));
+        }
+
+        test longest_wins() {
+            let mut table = Aliased::new(Mounted::new("server", server()));
+            table.alias("old", "server");
+            table.alias("old.host", "server.hostname");
+            let s = table.format("{old.host}, {old.port}");
+            assert_that!(&s, eq(Ok("example.org, 80".to_string())));
+        }
+
+        test unmatched_prefix() {
+            let mut inner: HashMap<&str, Box<dyn Fmt>> = HashMap::new();
+            inner.insert("old", Box::new(Namespace(server())));
+            let mut table = Aliased::new(inner);
+            table.alias("old.host", "old.hostname");
+            let s = table.format("{old.host}, {old.port}");
+            assert_that!(&s, eq(Ok("example.org, 80".to_string())));
+            let s = table.format("{old.user}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("old.user".to_string(), vec![]))));
+        }
+
+        test reporting() {
+            let hits = Rc::new(RefCell::new(Vec::new()));
+            let reported = hits.clone();
+            let mut table = Aliased::new(Mounted::new("server", server()));
+            table.alias("old.host", "server.hostname");
+            table.set_reporter(move |alias, canonical| {
+                reported.borrow_mut().push((alias.to_string(), canonical.to_string()));
+            });
+            table.format("{old.host}, {old.host}, {server.port}").unwrap();
+            assert_that!(&*hits.borrow(), eq(vec![
+                ("old.host".to_string(), "server.hostname".to_string()),
+                ("old.host".to_string(), "server.hostname".to_string()),
+            ]));
+        }
+
+        test strict_reporting() {
+            let hits = Rc::new(RefCell::new(Vec::new()));
+            let reported = hits.clone();
+            let mut table = Aliased::new(Mounted::new("server", server()));
+            table.alias("old.host", "server.hostname");
+            table.set_reporter(move |alias, _| reported.borrow_mut().push(alias.to_string()));
+            let context = Context { strict: true, ..Context::default() };
+            table.format_with("{old.host}, {old.host::width=r12}", &context).unwrap();
+            assert_that!(&*hits.borrow(), eq(vec!["old.host".to_string(), "old.host".to_string()]));
+        }
+
+        test unknown_canonical() {
+            let mut table = Aliased::new(server());
+            table.alias("user", "login");
+            let s = table.format("{user}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("user".to_string(), vec![]))));
+        }
+
+    }
+
+    test_suite! {
+        name cached;
+        use std::cell::Cell;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+        use galvanic_assert::matchers::*;
+
+        use adapters::{FnFmt, FnTable};
+        use functions;
+        use tables::Cached;
+        use value::Value;
+        use {util, Fmt, FormatTable, FormattingError, SingleFmtError};
+
+        fn counting_table(lookups: Rc<Cell<usize>>, formats: Rc<Cell<usize>>)
+            -> FnTable<impl Fn(&str) -> Option<Box<dyn Fmt>>>
+        {
+            FnTable::new(move |name| {
+                lookups.set(lookups.get() + 1);
+                if name != "v" {
+                    return None;
+                }
+                let formats = formats.clone();
+                Some(Box::new(FnFmt::new(move |_, args, _, options| {
+                    formats.set(formats.get() + 1);
+                    let mut s = format!("v{}", args.join(""));
+                    util::apply_common_options(&mut s, options)?;
+                    Ok(s)
+                })) as Box<dyn Fmt>)
+            })
+        }
+
+        /// Sums its arguments when given them as values, and counts how
+        /// many times it does.
+        struct Sum(Rc<Cell<usize>>);
+
+        impl Fmt for Sum {
+            fn format(&self, _: &[String], _: &[String], args: &[String], _: &[char],
+                      _: &HashMap<String, String>) -> Result<String, SingleFmtError> {
+                Ok(args.join("+"))
+            }
+
+            fn format_values(&self, _: &[String], _: &[String], args: &[Value], _: &[char],
+                             _: &HashMap<String, Value>) -> Result<String, FormattingError> {
+                self.0.set(self.0.get() + 1);
+                let mut sum = 0;
+                for arg in args {
+                    sum += arg.as_int()?;
+                }
+                Ok(sum.to_string())
+            }
+        }
+
+        test fmts_are_reused() {
+            let lookups = Rc::new(Cell::new(0));
+            let formats = Rc::new(Cell::new(0));
+            let table = counting_table(lookups.clone(), formats.clone());
+            let cached = Cached::new(&table);
+            let s = cached.format("{v}{v}{v::width=r3}");
+            assert_that!(&s, eq(Ok("vv  v".to_string())));
+            assert_that!(&lookups.get(), eq(1));
+            assert_that!(&formats.get(), eq(3));
+        }
+
+        test misses_are_cached() {
+            let lookups = Rc::new(Cell::new(0));
+            let formats = Rc::new(Cell::new(0));
+            let table = counting_table(lookups.clone(), formats.clone());
+            let cached = Cached::new(&table);
+            let s = cached.format("{x}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("x".to_string(), vec![]))));
+            let _ = cached.format("{x}");
+            assert_that!(&lookups.get(), eq(1));
+        }
+
+        test outputs() {
+            let lookups = Rc::new(Cell::new(0));
+            let formats = Rc::new(Cell::new(0));
+            let table = counting_table(lookups.clone(), formats.clone());
+            let mut cached = Cached::new(&table);
+            cached.set_cache_output(true);
+            let s = cached.format("{v}{v}{v{1}}{v{1}}{v::width=r3}{v::width=r3}");
+            assert_that!(&s, eq(Ok("vvv1v1  v  v".to_string())));
+            assert_that!(&formats.get(), eq(3));
+        }
+
+        test clear() {
+            let lookups = Rc::new(Cell::new(0));
+            let formats = Rc::new(Cell::new(0));
+            let table = counting_table(lookups.clone(), formats.clone());
+            let mut cached = Cached::new(&table);
+            cached.set_cache_output(true);
+            cached.format("{v}").unwrap();
+            cached.clear();
+            cached.format("{v}").unwrap();
+            assert_that!(&lookups.get(), eq(2));
+            assert_that!(&formats.get(), eq(2));
+        }
+
+        test outputs_of_lazy_fmts() {
+            let mut table = functions::table();
+            table.insert("i", &40);
+            let mut cached = Cached::new(&table);
+            cached.set_cache_output(true);
+            let s = cached.format("{if{1:a:{missing}}} {if{1:a:{missing}}} {if{{i}:b:c}}");
+            assert_that!(&s, eq(Ok("a a b".to_string())));
+            let s = cached.format("{if{0:a:{missing}}}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownFmt("missing".to_string(), vec![]))));
+        }
+
+        test outputs_of_typed_fmts() {
+            let formats = Rc::new(Cell::new(0));
+            let sum = Sum(formats.clone());
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("sum", &sum);
+            let mut cached = Cached::new(&table);
+            cached.set_cache_output(true);
+            let s = cached.format("{sum{40:2}} {sum{40:2}} {sum{1:2}}");
+            assert_that!(&s, eq(Ok("42 42 3".to_string())));
+            assert_that!(&formats.get(), eq(2));
+        }
+
+    }
+}
@@ -0,0 +1,201 @@
+//! Named templates that can be included into other templates.
+//!
+//! A `TemplateRegistry` holds format strings under names. Pass it to
+//! `FormatTable::format_with` as a part of a `Context`, and a placeholder of
+//! the form `{>name}` is replaced with the template registered under `name`,
+//! formatted with the same table and context as the string it's included in.
+//! Included templates can include other templates in turn, up to a depth of
+//! `MAX_INCLUDE_DEPTH`, which also stops include cycles.
+//! ```
+//! use std::collections::HashMap;
+//! use pfmt::{Context, Fmt, FormatTable};
+//! use pfmt::templates::TemplateRegistry;
+//!
+//! let mut templates = TemplateRegistry::new();
+//! templates.register("host", "{name} ({ip})").unwrap();
+//! templates.register("header", "== {>host} ==").unwrap();
+//! let name = "db1";
+//! let ip = "10.0.0.7";
+//! let mut table: HashMap<&str, &Fmt> = HashMap::new();
+//! table.insert("name", &name);
+//! table.insert("ip", &ip);
+//! let context = Context { templates: Some(&templates), ..Context::default() };
+//! let s = table.format_with("{>header}\nup", &context).unwrap();
+//! assert_eq!(s, "== db1 (10.0.0.7) ==\nup");
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use parse::{parse, Piece};
+use syntax::Syntax;
+use FormattingError;
+
+/// How deep includes can nest before formatting fails with
+/// `FormattingError::IncludeDepthExceeded`.
+pub const MAX_INCLUDE_DEPTH: u8 = 32;
+
+/// A collection of named templates, available to the include syntax.
+#[derive(Default)]
+pub struct TemplateRegistry {
+    templates: HashMap<String, Vec<Piece>>,
+    syntax: Syntax,
+}
+
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        TemplateRegistry {
+            templates: HashMap::new(),
+            syntax: Syntax::default(),
+        }
+    }
+
+    /// Creates a registry whose templates are written in the given syntax,
+    /// which doesn't have to be the one of the strings including them.
+    pub fn with_syntax(syntax: Syntax) -> Self {
+        TemplateRegistry {
+            templates: HashMap::new(),
+            syntax,
+        }
+    }
+
+    /// Registers a template under the given name, replacing any template
+    /// that was registered under it before. The template is parsed right
+    /// away, so syntax errors are reported here rather than when it's
+    /// included. Templates it includes don't have to be registered yet.
+    pub fn register(&mut self, name: &str, template: &str) -> Result<(), FormattingError> {
+        let pieces = parse(template, &self.syntax)?;
+        self.templates.insert(name.to_string(), pieces);
+        Ok(())
+    }
+
+    /// Removes a template from the registry, returning whether it was there.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.templates.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&[Piece]> {
+        self.templates.get(name).map(|pieces| pieces.as_slice())
+    }
+}
+
+impl fmt::Debug for TemplateRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names: Vec<&String> = self.templates.keys().collect();
+        names.sort();
+        f.debug_struct("TemplateRegistry").field("templates", &names).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name includes;
+        use std::collections::HashMap;
+        use galvanic_assert::matchers::*;
+
+        use escape::Escaper;
+        use templates::{TemplateRegistry, MAX_INCLUDE_DEPTH};
+        use {Context, Fmt, FormatTable, FormattingError};
+
+        fn table() -> HashMap<&'static str, &'static dyn Fmt> {
+            let mut table: HashMap<&'static str, &'static dyn Fmt> = HashMap::new();
+            table.insert("a", &"<a>");
+            table.insert("i", &12);
+            table
+        }
+
+        test simple() {
+            let mut templates = TemplateRegistry::new();
+            templates.register("t", "[{a}]").unwrap();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let s = table().format_with("{>t} {> t }", &context);
+            assert_that!(&s, eq(Ok("[<a>] [<a>]".to_string())));
+        }
+
+        test nested() {
+            let mut templates = TemplateRegistry::new();
+            templates.register("outer", "({>inner})").unwrap();
+            templates.register("inner", "{i:x}").unwrap();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let s = table().format_with("{>outer}", &context);
+            assert_that!(&s, eq(Ok("(c)".to_string())));
+        }
+
+        test in_arguments() {
+            let mut templates = TemplateRegistry::new();
+            templates.register("t", "{i}").unwrap();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let mut table = ::functions::table();
+            table.insert("i", &12);
+            let s = table.format_with("{repeat{x:{>t}}}", &context);
+            assert_that!(&s, eq(Ok("xxxxxxxxxxxx".to_string())));
+        }
+
+        test escaping() {
+            let mut templates = TemplateRegistry::new();
+            templates.register("t", "<b>{a}</b>").unwrap();
+            let context = Context {
+                escaper: Some(Escaper::Html),
+                templates: Some(&templates),
+                ..Context::default()
+            };
+            let s = table().format_with("{>t}", &context);
+            assert_that!(&s, eq(Ok("<b>&lt;a&gt;</b>".to_string())));
+        }
+
+        test column() {
+            let mut templates = TemplateRegistry::new();
+            templates.register("t", "{a} {s::wrap=14}").unwrap();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let mut table = table();
+            table.insert("s", &"bb cc dd");
+            let s = table.format_with("1234 {>t}", &context);
+            assert_that!(&s, eq(Ok("1234 <a> bb cc\ndd".to_string())));
+        }
+
+        test unknown() {
+            let templates = TemplateRegistry::new();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let s = table().format_with("{>t}", &context);
+            assert_that!(&s, eq(Err(FormattingError::UnknownTemplate("t".to_string()))));
+            let s = table().format("{>t}");
+            assert_that!(&s, eq(Err(FormattingError::UnknownTemplate("t".to_string()))));
+        }
+
+        test cycle() {
+            let mut templates = TemplateRegistry::new();
+            templates.register("a", "a{>b}").unwrap();
+            templates.register("b", "b{>a}").unwrap();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let s = table().format_with("{>a}", &context);
+            assert_that!(&s, eq(Err(FormattingError::IncludeDepthExceeded("a".to_string()))));
+        }
+
+        test depth_limit() {
+            let mut templates = TemplateRegistry::new();
+            for i in 0..MAX_INCLUDE_DEPTH {
+                templates.register(&i.to_string(), &format!("{{>{}}}", i + 1)).unwrap();
+            }
+            templates.register(&MAX_INCLUDE_DEPTH.to_string(), "end").unwrap();
+            let context = Context { templates: Some(&templates), ..Context::default() };
+            let s = table().format_with("{>1}", &context);
+            assert_that!(&s, eq(Ok("end".to_string())));
+            let s = table().format_with("{>0}", &context);
+            assert_that!(&s, eq(Err(FormattingError::IncludeDepthExceeded(
+                MAX_INCLUDE_DEPTH.to_string()))));
+        }
+
+        test syntax_errors() {
+            let mut templates = TemplateRegistry::new();
+            let res = templates.register("t", "{a");
+            assert_that!(&res, eq(Err(FormattingError::UnterminatedPlaceholder("{a".to_string()))));
+            assert_that!(&templates.contains("t"), eq(false));
+        }
+
+    }
+}
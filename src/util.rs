@@ -1,11 +1,34 @@
 use std::collections::HashMap;
-use std::iter::repeat;
 use std::string::ToString;
 
 use num;
 
+use escape::Escaper;
 use {SingleFmtError, SingleFmtError::*};
 
+/* ---------- flags and options known to the standard types ---------- */
+
+/// The common options, along with those the formatting itself handles for
+/// every `Fmt` (`filter` and `escape`).
+pub const COMMON_OPTIONS: &[&str] = &[
+    "wrap", "indent", "prefix", "column", "truncate", "width", "block", "filter", "escape",
+];
+
+/// The common options along with the common numeric ones.
+pub const NUMERIC_OPTIONS: &[&str] = &[
+    "wrap", "indent", "prefix", "column", "truncate", "width", "block", "filter", "escape",
+    "prec", "round",
+];
+
+/// The flags of `bool`.
+pub const BOOL_FLAGS: &[char] = &['y', 'Y'];
+
+/// The flags of the floating point types.
+pub const FLOAT_FLAGS: &[char] = &['+', 'e'];
+
+/// The flags of the integer types.
+pub const INT_FLAGS: &[char] = &['+', 'b', 'o', 'p', 'x'];
+
 /* ---------- general formatting options ---------- */
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -19,11 +42,57 @@ pub fn apply_common_options(
     s: &mut String,
     options: &HashMap<String, String>,
 ) -> Result<(), SingleFmtError> {
+    apply_wrapping(s, options)?;
     apply_truncation(s, options)?;
     apply_width(s, options)?;
     Ok(())
 }
 
+pub fn apply_wrapping(
+    s: &mut String,
+    options: &HashMap<String, String>,
+) -> Result<(), SingleFmtError> {
+    let width = match get_unsigned(options, "wrap")? {
+        Some(width) => width,
+        None => return Ok(()),
+    };
+    let column = get_unsigned(options, "column")?.unwrap_or(0);
+    let mut continuation = " ".repeat(get_unsigned(options, "indent")?.unwrap_or(0));
+    if let Some(prefix) = options.get("prefix") {
+        continuation.push_str(prefix);
+    }
+    let continuation_len = continuation.chars().count();
+    let mut res = String::with_capacity(s.len());
+    let mut line_len = column;
+    let mut line_is_empty = true;
+    for (i, paragraph) in s.split('\n').enumerate() {
+        if i > 0 {
+            res.push('\n');
+            res.push_str(&continuation);
+            line_len = continuation_len;
+            line_is_empty = true;
+        }
+        for word in paragraph.split_whitespace() {
+            let word_len = word.chars().count();
+            if !line_is_empty && line_len + 1 + word_len > width {
+                res.push('\n');
+                res.push_str(&continuation);
+                line_len = continuation_len;
+                line_is_empty = true;
+            }
+            if !line_is_empty {
+                res.push(' ');
+                line_len += 1;
+            }
+            res.push_str(word);
+            line_len += word_len;
+            line_is_empty = false;
+        }
+    }
+    *s = res;
+    Ok(())
+}
+
 pub fn apply_width(
     s: &mut String,
     options: &HashMap<String, String>,
@@ -41,27 +110,10 @@ pub fn apply_width(
             }
         };
         if let Ok(width) = width_str[1..].parse::<usize>() {
-            let len = s.chars().count();
-            if len > width {
-                return Ok(());
-            }
-            let delta = width - len;
-            match justification {
-                Justification::Left() => {
-                    let padding: String = repeat(' ').take(delta).collect();
-                    s.push_str(&padding);
-                }
-                Justification::Center() => {
-                    let left = delta / 2;
-                    let right = delta - left;
-                    let leftstr: String = repeat(' ').take(left).collect();
-                    let rightstr: String = repeat(' ').take(right).collect();
-                    *s = leftstr + s + &rightstr;
-                }
-                Justification::Right() => {
-                    let padding: String = repeat(' ').take(delta).collect();
-                    *s = padding + s;
-                }
+            if options.contains_key("block") {
+                justify_block(s, width, justification);
+            } else {
+                *s = map_lines(s, |line| justify_line(line, width, 0, justification));
             }
         } else {
             return Err(InvalidOptionValue(
@@ -95,15 +147,16 @@ pub fn apply_truncation(
             }
         };
         if let Ok(truncate_to_width) = opt_str[1..].parse::<usize>() {
-            let len = s.chars().count();
-            if len < truncate_to_width {
-                return Ok(());
-            }
-            if is_left {
-                *s = s.chars().skip(len - truncate_to_width).collect();
-            } else {
-                *s = s.chars().take(truncate_to_width).collect();
-            }
+            *s = map_lines(s, |line| {
+                let len = line.chars().count();
+                if len < truncate_to_width {
+                    line.to_string()
+                } else if is_left {
+                    line.chars().skip(len - truncate_to_width).collect()
+                } else {
+                    line.chars().take(truncate_to_width).collect()
+                }
+            });
             Ok(())
         } else {
             Err(InvalidOptionValue(
@@ -116,6 +169,51 @@ pub fn apply_truncation(
     }
 }
 
+/// Pads a single line to the given width. `len` is the length the line is
+/// justified as, which may be larger than its actual length.
+fn justify_line(line: &str, width: usize, len: usize, justification: Justification) -> String {
+    let line_len = line.chars().count();
+    let len = len.max(line_len);
+    if len > width {
+        return line.to_string();
+    }
+    let delta = width - len;
+    let left = match justification {
+        Justification::Left() => 0,
+        Justification::Center() => delta / 2,
+        Justification::Right() => delta,
+    };
+    let right = width - left - line_len;
+    " ".repeat(left) + line + &" ".repeat(right)
+}
+
+/// Justifies all the lines as a single block as wide as its widest line, so
+/// that they keep their alignment relative to each other.
+fn justify_block(s: &mut String, width: usize, justification: Justification) {
+    let widest = s.split('\n').map(|line| line.chars().count()).max().unwrap_or(0);
+    let width = width.max(widest);
+    *s = map_lines(s, |line| justify_line(line, width, widest, justification));
+}
+
+fn map_lines<F: Fn(&str) -> String>(s: &str, f: F) -> String {
+    s.split('\n').map(f).collect::<Vec<_>>().join("\n")
+}
+
+/* ---------- escaping ---------- */
+
+/// Escapes `s` as requested by the `escape` option, if there is one. The value
+/// `none` disables escaping, which is mainly useful to opt a single placeholder
+/// out of the escaper set for the whole formatting call.
+pub fn apply_escaping(s: String, options: &HashMap<String, String>) -> Result<String, SingleFmtError> {
+    match options.get("escape").map(|v| v.as_str()) {
+        None | Some("none") => Ok(s),
+        Some(name) => match Escaper::from_name(name) {
+            Some(escaper) => Ok(escaper.escape(&s)),
+            None => Err(InvalidOptionValue("escape".to_string(), name.to_string())),
+        },
+    }
+}
+
 /* ---------- numerical formatting ---------- */
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -231,8 +329,68 @@ pub fn join_name(name: &[String]) -> String {
     res
 }
 
+/// Returns the column the text would end at if it was output starting at the
+/// given column.
+pub fn column_after(column: usize, s: &str) -> usize {
+    match s.rfind('\n') {
+        Some(pos) => s[pos + 1..].chars().count(),
+        None => column + s.chars().count(),
+    }
+}
+
+/// The edit distance between two strings, counted in characters: the number
+/// of insertions, deletions, substitutions and swaps of adjacent characters
+/// it takes to turn one into the other (without editing any part twice).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut before_previous: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            row[j] = (previous[j - 1] + cost)
+                .min(previous[j] + 1)
+                .min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        before_previous = previous;
+        previous = row;
+    }
+    previous[b.len()]
+}
+
+/// Picks the candidates that are close enough to the name to be what was
+/// meant by it, the closest ones first. A candidate is close enough when it
+/// takes at most one edit for every three characters of the name (but at
+/// least one) to turn the name into it.
+pub fn close_matches<S: AsRef<str>>(name: &str, candidates: &[S]) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate.as_ref()), candidate.as_ref()))
+        .filter(|&(distance, _)| distance > 0 && distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches.into_iter().map(|(_, candidate)| candidate.to_string()).collect()
+}
+
 /* ---------- helpers ---------- */
 
+fn get_unsigned(options: &HashMap<String, String>, name: &str) -> Result<Option<usize>, SingleFmtError> {
+    match options.get(name) {
+        Some(s) => match s.parse::<usize>() {
+            Ok(i) => Ok(Some(i)),
+            Err(_) => Err(InvalidOptionValue(name.to_string(), s.to_string())),
+        },
+        None => Ok(None),
+    }
+}
+
 fn get_precision(options: &HashMap<String, String>) -> Result<Option<i32>, SingleFmtError> {
     if let Some(s) = options.get("prec") {
         if let Ok(i) = s.parse::<i32>() {
@@ -0,0 +1,294 @@
+//! Lazily evaluated arguments and options.
+//!
+//! `Fmt::format` receives its arguments and options already formatted into
+//! strings. `Fmt::format_values` receives them as `Value`s instead, which are
+//! only evaluated when asked to, and which remember what they were evaluated
+//! from: a `Value` can be rendered into a string, parsed as a number, or, if
+//! it's a placeholder, used to reach the `Fmt` behind it.
+//! ```
+//! use std::collections::HashMap;
+//! use pfmt::{Fmt, FormatTable, FormattingError, SingleFmtError};
+//! use pfmt::value::Value;
+//!
+//! /// Sums its arguments, without ever looking at their string forms.
+//! struct Sum;
+//!
+//! impl Fmt for Sum {
+//!     fn format(
+//!         &self,
+//!         _full_name: &[String],
+//!         _name: &[String],
+//!         args: &[String],
+//!         _flags: &[char],
+//!         _options: &HashMap<String, String>,
+//!     ) -> Result<String, SingleFmtError> {
+//!         Ok(args.join("+"))
+//!     }
+//!
+//!     fn format_values(
+//!         &self,
+//!         _full_name: &[String],
+//!         _name: &[String],
+//!         args: &[Value],
+//!         _flags: &[char],
+//!         _options: &HashMap<String, Value>,
+//!     ) -> Result<String, FormattingError> {
+//!         let mut sum = 0;
+//!         for arg in args {
+//!             sum += arg.as_int()?;
+//!         }
+//!         Ok(sum.to_string())
+//!     }
+//! }
+//!
+//! let i = 40;
+//! let mut table: HashMap<&str, &Fmt> = HashMap::new();
+//! table.insert("i", &i);
+//! table.insert("sum", &Sum);
+//! let s = table.format("{sum{{i}:2}}").unwrap();
+//! assert_eq!(s, "42");
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use parse::Piece;
+use {BoxOrRef, Fmt, FormattingError};
+
+/// Evaluates parts of a format string on behalf of a `Value`, so that it
+/// doesn't need to know the type of the format table.
+pub(crate) trait Evaluator {
+    fn evaluate(&self, piece: &Piece) -> Result<String, FormattingError>;
+    fn lookup<'a>(&'a self, name: &str) -> Option<BoxOrRef<'a, dyn Fmt>>;
+}
+
+/// An argument or an option of a placeholder. It's evaluated the first time
+/// it's rendered, and the result is remembered for subsequent calls.
+pub struct Value<'a> {
+    source: Option<(&'a Piece, &'a dyn Evaluator)>,
+    rendered: RefCell<Option<String>>,
+}
+
+impl<'a> Value<'a> {
+    pub(crate) fn lazy(piece: &'a Piece, evaluator: &'a dyn Evaluator) -> Self {
+        Value {
+            source: Some((piece, evaluator)),
+            rendered: RefCell::new(None),
+        }
+    }
+
+    /// Formats the value into a string.
+    pub fn render(&self) -> Result<String, FormattingError> {
+        if let Some(s) = self.rendered.borrow().as_ref() {
+            return Ok(s.clone());
+        }
+        let s = match self.source {
+            Some((piece, evaluator)) => evaluator.evaluate(piece)?,
+            None => String::new(),
+        };
+        *self.rendered.borrow_mut() = Some(s.clone());
+        Ok(s)
+    }
+
+    /// Renders the value and parses it as an integer. Fails with
+    /// `FormattingError::NotANumber` if it isn't one.
+    pub fn as_int(&self) -> Result<i64, FormattingError> {
+        let s = self.render()?;
+        s.trim().parse().map_err(|_| FormattingError::NotANumber(s))
+    }
+
+    /// Renders the value and parses it as a floating point number. Fails with
+    /// `FormattingError::NotANumber` if it isn't one.
+    pub fn as_float(&self) -> Result<f64, FormattingError> {
+        let s = self.render()?;
+        s.trim().parse().map_err(|_| FormattingError::NotANumber(s))
+    }
+
+    /// If the value is a placeholder, returns its full name.
+    pub fn name(&self) -> Option<&'a [String]> {
+        match self.source {
+            Some((Piece::Placeholder(name, ..), _)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// If the value is a placeholder, returns the `Fmt` its name starts with,
+    /// as found in the format table. The rest of the name is up to the
+    /// caller, so the `Fmt` can be asked for any of its sub-`Fmt`s.
+    pub fn get_fmt(&self) -> Option<BoxOrRef<'a, dyn Fmt>> {
+        match self.source {
+            Some((Piece::Placeholder(name, ..), evaluator)) => evaluator.lookup(&name[0]),
+            _ => None,
+        }
+    }
+
+    /// If the value is a placeholder, formats it as if its name was followed
+    /// by the given sub-name, keeping its arguments, flags and options. So
+    /// for the value `{user}`, `render_sub(&["name".to_string()])` gives the
+    /// same result as `{user.name}` would.
+    pub fn render_sub(&self, sub_name: &[String]) -> Option<Result<String, FormattingError>> {
+        match self.source {
+            Some((Piece::Placeholder(name, args, flags, opts, filters), evaluator)) => {
+                let mut name = name.clone();
+                name.extend_from_slice(sub_name);
+                let piece = Piece::Placeholder(
+                    name,
+                    args.clone(),
+                    flags.clone(),
+                    opts.clone(),
+                    filters.clone(),
+                );
+                Some(evaluator.evaluate(&piece))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<String> for Value<'a> {
+    fn from(s: String) -> Self {
+        Value {
+            source: None,
+            rendered: RefCell::new(Some(s)),
+        }
+    }
+}
+
+impl<'a, 'b> From<&'b str> for Value<'a> {
+    fn from(s: &'b str) -> Self {
+        Value::from(s.to_string())
+    }
+}
+
+impl<'a> fmt::Debug for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.source {
+            Some((piece, _)) => f.debug_tuple("Value").field(piece).finish(),
+            None => f.debug_tuple("Value").field(&self.rendered.borrow()).finish(),
+        }
+    }
+}
+
+/// Renders all the arguments and options, in order to pass them to
+/// `Fmt::format`.
+pub fn render_all(
+    args: &[Value],
+    options: &HashMap<String, Value>,
+) -> Result<(Vec<String>, HashMap<String, String>), FormattingError> {
+    let mut rendered_args = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        rendered_args.push(arg.render()?);
+    }
+    let mut rendered_options = HashMap::new();
+    for (key, value) in options.iter() {
+        rendered_options.insert(key.clone(), value.render()?);
+    }
+    Ok((rendered_args, rendered_options))
+}
+
+#[cfg(test)]
+mod tests {
+    test_suite! {
+        name values;
+        use std::cell::Cell;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+        use galvanic_assert::matchers::*;
+
+        use adapters::FnFmt;
+        use value::Value;
+        use {Fmt, FormatTable, FormattingError, SingleFmtError};
+
+        struct Probe<F>(F);
+
+        impl<F> Fmt for Probe<F>
+        where
+            F: Fn(&[Value], &HashMap<String, Value>) -> Result<String, FormattingError>,
+        {
+            fn format(
+                &self,
+                _full_name: &[String],
+                _name: &[String],
+                _args: &[String],
+                _flags: &[char],
+                _options: &HashMap<String, String>,
+            ) -> Result<String, SingleFmtError> {
+                unreachable!()
+            }
+
+            fn format_values(
+                &self,
+                _full_name: &[String],
+                _name: &[String],
+                args: &[Value],
+                _flags: &[char],
+                options: &HashMap<String, Value>,
+            ) -> Result<String, FormattingError> {
+                (self.0)(args, options)
+            }
+        }
+
+        test numbers() {
+            let probe = Probe(|args: &[Value], options: &HashMap<String, Value>| {
+                let x = args[0].as_float()? * options["times"].as_int()? as f64;
+                Ok(x.to_string())
+            });
+            let f = 1.25;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("p", &probe);
+            table.insert("f", &f);
+            let s = table.format("{p{{f}}::times=4}");
+            assert_that!(&s, eq(Ok("5".to_string())));
+            let s = table.format("{p{x}::times=4}");
+            assert_that!(&s, eq(Err(FormattingError::NotANumber("x".to_string()))));
+        }
+
+        test lazy() {
+            let calls = Rc::new(Cell::new(0));
+            let count = calls.clone();
+            let counter = FnFmt::new(move |_, _, _, _| {
+                count.set(count.get() + 1);
+                Ok("c".to_string())
+            });
+            let probe = Probe(|args: &[Value], _: &HashMap<String, Value>| {
+                Ok(format!("{}{}", args[0].render()?, args[0].render()?))
+            });
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("p", &probe);
+            table.insert("c", &counter);
+            let s = table.format("{p{{c}:{c}:{missing}}}");
+            assert_that!(&s, eq(Ok("cc".to_string())));
+            assert_that!(&calls.get(), eq(1));
+        }
+
+        test sub_fmts() {
+            let probe = Probe(|args: &[Value], _: &HashMap<String, Value>| {
+                let name = args[0].name().map(|n| n.join("."));
+                let sub = args[0].render_sub(&["b".to_string()]).unwrap()?;
+                Ok(format!("{:?} {}", name, sub))
+            });
+            let mut inner: HashMap<&str, &dyn Fmt> = HashMap::new();
+            inner.insert("b", &1);
+            let ns = ::tables::Namespace(inner);
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("p", &probe);
+            table.insert("a", &ns);
+            let s = table.format("{p{{a}}}");
+            assert_that!(&s, eq(Ok("Some(\"a\") 1".to_string())));
+        }
+
+        test plain_fmts_get_rendered_values() {
+            let f = FnFmt::new(|_, args, _, options| {
+                Ok(format!("{}/{}", args.join(","), options["o"]))
+            });
+            let i = 3;
+            let mut table: HashMap<&str, &dyn Fmt> = HashMap::new();
+            table.insert("f", &f);
+            table.insert("i", &i);
+            let s = table.format("{f{{i}:x}::o={i}}");
+            assert_that!(&s, eq(Ok("3,x/3".to_string())));
+        }
+
+    }
+}